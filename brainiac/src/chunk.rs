@@ -0,0 +1,130 @@
+/// Rough number of tokens produced per whitespace separated word by common tokenizers.
+const TOKENS_PER_WORD: f64 = 1.35;
+
+/// Estimate the number of model tokens in `text`.
+/// This avoids pulling in a model specific tokenizer and errs on the side of overestimating.
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.split_whitespace().count() as f64 * TOKENS_PER_WORD).ceil() as usize
+}
+
+/// Split `text` into chunks of at most `max_tokens` estimated tokens.
+/// Chunks are built from whole paragraphs where possible. Paragraphs that are larger than
+/// `max_tokens` on their own are split on sentence boundaries and, failing that, on words.
+pub fn chunk_text(text: &str, max_tokens: usize) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let mut chunks = vec![];
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for piece in text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .flat_map(|p| split_oversized(p, max_tokens))
+    {
+        let tokens = estimate_tokens(&piece);
+        if current_tokens + tokens > max_tokens && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&piece);
+        current_tokens += tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn split_oversized(paragraph: &str, max_tokens: usize) -> Vec<String> {
+    if estimate_tokens(paragraph) <= max_tokens {
+        return vec![paragraph.to_string()];
+    }
+    let mut pieces = vec![];
+    let mut current = String::new();
+    for sentence in paragraph.split_inclusive(['.', '!', '?']) {
+        let sentence = sentence.trim();
+        if estimate_tokens(sentence) > max_tokens {
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            pieces.extend(split_words(sentence, max_tokens));
+            continue;
+        }
+        if estimate_tokens(&current) + estimate_tokens(sentence) > max_tokens && !current.is_empty()
+        {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(sentence);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+fn split_words(sentence: &str, max_tokens: usize) -> Vec<String> {
+    let words_per_piece = ((max_tokens as f64 / TOKENS_PER_WORD).floor() as usize).max(1);
+    sentence
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .chunks(words_per_piece)
+        .map(|words| words.join(" "))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_tokens_rounds_up() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("one"), 2);
+        assert_eq!(estimate_tokens("one two three four"), 6);
+    }
+
+    #[test]
+    fn chunk_text_packs_whole_paragraphs() {
+        // Every paragraph is 2 words, about 3 tokens
+        let text = "one two\n\nthree four\n\n\n\nfive six\n\nseven eight";
+        assert_eq!(
+            chunk_text(text, 6),
+            ["one two\n\nthree four", "five six\n\nseven eight"]
+        );
+        assert_eq!(chunk_text(text, 100), [text.replace("\n\n\n\n", "\n\n")]);
+    }
+
+    #[test]
+    fn chunk_text_splits_oversized_paragraphs_on_sentences() {
+        let text = "First short one. Second short one. Third short one.";
+        assert_eq!(
+            chunk_text(text, 6),
+            ["First short one.", "Second short one.", "Third short one."]
+        );
+    }
+
+    #[test]
+    fn oversized_sentences_are_split_on_words() {
+        let sentence = "one two three four five six seven";
+        assert_eq!(
+            split_oversized(sentence, 4),
+            ["one two", "three four", "five six", "seven"]
+        );
+        for chunk in chunk_text(sentence, 4) {
+            assert!(estimate_tokens(&chunk) <= 4);
+        }
+    }
+
+    #[test]
+    fn chunks_smaller_than_a_word_hold_one_word() {
+        assert_eq!(split_words("one two three", 1), ["one", "two", "three"]);
+        assert_eq!(chunk_text("one two three", 0), ["one", "two", "three"]);
+    }
+}
@@ -0,0 +1,692 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use gray_matter::ParsedEntityStruct;
+use ollama_rs::Ollama;
+use slugify_rs::slugify;
+
+use crate::config::Config;
+use crate::error::BrainiacError;
+use crate::keywords::{self, KeywordSettings, KeywordSource};
+use crate::lint::LintIssue;
+use crate::model::{AnalyticsMetadata, InterestMetadata, Metadata, ResponseOutputType};
+use crate::ollama::{
+    formatter::OutputFormatter,
+    generator::{OutputGenerator, TokenCallback},
+};
+use crate::output::{self, Backup, CollisionPolicy, WriteStatus};
+use crate::taxonomy::{GenreMatch, Taxonomy, LOW_CONFIDENCE};
+use crate::{
+    create_output_file_name, generate_article_matter, get_analytics_data, history, lint, markdown,
+    parse_article_matter, sanitize_string, title, BrainiacAppendOutput, BrainiacGenerate,
+    GeneratedDocument, InputSource, OutputTarget, Timings, DEFAULT_CHUNK_THRESHOLD,
+};
+
+/// The model used for generating metadata when none is set
+pub const DEFAULT_GEN_MODEL: &str = "deepseek-r1:8b";
+/// The model used for formatting model responses when none is set
+pub const DEFAULT_FORMAT_MODEL: &str = "deepseek-r1:1.5b";
+
+/// What to do with a generated field after reviewing it
+pub enum Review {
+    /// Use the generated value
+    Accept,
+    /// Use this value instead, lists are comma separated
+    Replace(String),
+    /// Ask the model for a new value
+    Regenerate,
+}
+
+/// Called with every generated field, its value and the ranked alternates for that value.
+/// Lists are joined with commas.
+pub type ReviewCallback = Arc<dyn Fn(ResponseOutputType, &str, &[String]) -> Review + Send + Sync>;
+
+struct GeneratedFields {
+    title: String,
+    alternate_titles: Vec<String>,
+    description: String,
+    genres: Vec<String>,
+    keywords: Vec<String>,
+    timings: Timings,
+}
+
+/// A long lived client that keeps its model connection and settings across many articles.
+/// Create one with `Brainiac::builder()`.
+#[derive(Clone)]
+pub struct Brainiac {
+    instance: Ollama,
+    gen_model: String,
+    format_model: String,
+    author: String,
+    chunk_threshold: usize,
+    readability: bool,
+    system_prompt: Option<String>,
+    on_token: Option<TokenCallback>,
+    on_review: Option<ReviewCallback>,
+    title_candidates: usize,
+    taxonomy: Taxonomy,
+    keywords: KeywordSettings,
+    on_collision: CollisionPolicy,
+    backup: Option<Backup>,
+}
+
+/// Settings for a `Brainiac` client, every setting has a default
+#[derive(Clone, Default)]
+pub struct BrainiacBuilder {
+    host: Option<String>,
+    generate: BrainiacGenerate,
+    system_prompt: Option<String>,
+    on_token: Option<TokenCallback>,
+    on_review: Option<ReviewCallback>,
+    title_candidates: Option<usize>,
+    config: Config,
+    on_collision: CollisionPolicy,
+    backup: Option<Backup>,
+}
+
+impl BrainiacBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The url of the Ollama server, defaults to `http://127.0.0.1:11434`
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn gen_model(mut self, model: impl Into<String>) -> Self {
+        self.generate.gen_model = Some(model.into());
+        self
+    }
+
+    pub fn format_model(mut self, model: impl Into<String>) -> Self {
+        self.generate.format_model = Some(model.into());
+        self
+    }
+
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.generate.author = author.into();
+        self
+    }
+
+    /// Estimated token count above which the article is chunked and summarized before prompting
+    pub fn chunk_threshold(mut self, chunk_threshold: usize) -> Self {
+        self.generate.chunk_threshold = Some(chunk_threshold);
+        self
+    }
+
+    /// Include readability scores in the analytics metadata
+    pub fn readability(mut self, readability: bool) -> Self {
+        self.generate.readability = readability;
+        self
+    }
+
+    /// Replace the system prompt used for generating fields
+    pub fn system_prompt(mut self, system_prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(system_prompt.into());
+        self
+    }
+
+    /// Stream the generation of each field, passing every token to `on_token` as it arrives.
+    /// The complete response is still validated before it is used.
+    pub fn on_token(
+        mut self,
+        on_token: impl Fn(ResponseOutputType, &str) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_token = Some(Arc::new(on_token));
+        self
+    }
+
+    /// Review every generated field before it is used, see `Review`.
+    pub fn on_review(
+        mut self,
+        on_review: impl Fn(ResponseOutputType, &str, &[String]) -> Review + Send + Sync + 'static,
+    ) -> Self {
+        self.on_review = Some(Arc::new(on_review));
+        self
+    }
+
+    /// How many titles are generated and ranked, the others are kept as alternate titles
+    pub fn title_candidates(mut self, title_candidates: usize) -> Self {
+        self.title_candidates = Some(title_candidates);
+        self
+    }
+
+    /// Project wide settings such as the genre taxonomy and keyword vocabulary
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// What to do when the output file already exists
+    pub fn on_collision(mut self, on_collision: CollisionPolicy) -> Self {
+        self.on_collision = on_collision;
+        self
+    }
+
+    /// Keep a copy of any file that is replaced
+    pub fn backup(mut self, backup: Backup) -> Self {
+        self.backup = Some(backup);
+        self
+    }
+
+    /// Validate the settings and create the client.
+    pub fn build(self) -> Result<Brainiac, BrainiacError> {
+        let chunk_threshold = self
+            .generate
+            .chunk_threshold
+            .unwrap_or(DEFAULT_CHUNK_THRESHOLD);
+        if chunk_threshold == 0 {
+            return Err(BrainiacError::ConfigError(
+                None,
+                "chunk_threshold must be greater than 0".to_string(),
+            ));
+        }
+        let title_candidates = self.title_candidates.unwrap_or(1);
+        if title_candidates == 0 {
+            return Err(BrainiacError::ConfigError(
+                None,
+                "title_candidates must be greater than 0".to_string(),
+            ));
+        }
+        let instance = match self.host {
+            Some(host) => Ollama::try_new(host.as_str()).map_err(|error| {
+                BrainiacError::ConfigError(None, format!("invalid host {}: {}", host, error))
+            })?,
+            None => Ollama::default(),
+        };
+        Ok(Brainiac {
+            instance,
+            gen_model: self
+                .generate
+                .gen_model
+                .unwrap_or(DEFAULT_GEN_MODEL.to_string()),
+            format_model: self
+                .generate
+                .format_model
+                .unwrap_or(DEFAULT_FORMAT_MODEL.to_string()),
+            author: self.generate.author,
+            chunk_threshold,
+            readability: self.generate.readability,
+            system_prompt: self.system_prompt,
+            on_token: self.on_token,
+            on_review: self.on_review,
+            title_candidates,
+            taxonomy: self.config.taxonomy(),
+            keywords: self.config.keywords,
+            on_collision: self.on_collision,
+            backup: self.backup,
+        })
+    }
+}
+
+impl From<BrainiacGenerate> for BrainiacBuilder {
+    fn from(generate: BrainiacGenerate) -> Self {
+        Self {
+            generate,
+            ..Default::default()
+        }
+    }
+}
+
+impl Brainiac {
+    pub fn builder() -> BrainiacBuilder {
+        BrainiacBuilder::new()
+    }
+
+    /// A generator for `content`, condensed to fit the chunk threshold.
+    async fn generator(&self, content: String) -> Result<OutputGenerator<'_>, BrainiacError> {
+        let mut generator = OutputGenerator::new(&self.instance, self.gen_model.clone());
+        generator.set_content(content);
+        if let Some(system_prompt) = &self.system_prompt {
+            generator.set_system(system_prompt.clone());
+        }
+        if let Some(on_token) = &self.on_token {
+            generator.set_on_token(on_token.clone());
+        }
+        generator.set_genres(self.taxonomy.paths());
+        generator.condense_content(self.chunk_threshold).await?;
+        Ok(generator)
+    }
+
+    fn formatter(&self) -> OutputFormatter<'_> {
+        let mut formatter = OutputFormatter::new(&self.instance, self.format_model.clone());
+        formatter.set_genres(self.taxonomy.paths());
+        formatter
+    }
+
+    /// The review of a generated field, fields are accepted when no review callback is set.
+    fn review(&self, field: ResponseOutputType, value: &str, alternates: &[String]) -> Review {
+        match &self.on_review {
+            Some(on_review) => on_review(field, value, alternates),
+            None => Review::Accept,
+        }
+    }
+
+    /// Generate and format a single value.
+    async fn generate_value(
+        generator: &OutputGenerator<'_>,
+        formatter: &OutputFormatter<'_>,
+        field: ResponseOutputType,
+    ) -> Result<String, BrainiacError> {
+        let output = generator.generate_output(field).await?;
+        log::trace!("Unproccessed {}: {}\n", field, output.response);
+        let text = formatter
+            .format_text(sanitize_string(output.response), field)
+            .await?;
+        log::info!("{}: {}", field, text);
+        Ok(text)
+    }
+
+    /// Generate and format a single value, until the review accepts or replaces it.
+    async fn generate_text(
+        &self,
+        generator: &OutputGenerator<'_>,
+        formatter: &OutputFormatter<'_>,
+        field: ResponseOutputType,
+    ) -> Result<String, BrainiacError> {
+        loop {
+            let text = Self::generate_value(generator, formatter, field).await?;
+            match self.review(field, &text, &[]) {
+                Review::Accept => return Ok(text),
+                Review::Replace(text) => return Ok(text),
+                Review::Regenerate => continue,
+            }
+        }
+    }
+
+    /// Generate and format a list of values. Keywords are normalized.
+    async fn generate_list_value(
+        &self,
+        generator: &OutputGenerator<'_>,
+        formatter: &OutputFormatter<'_>,
+        field: ResponseOutputType,
+    ) -> Result<Vec<String>, BrainiacError> {
+        let output = generator.generate_output(field).await?;
+        log::trace!("Unproccessed {}: {}\n", field, output.response);
+        let content = sanitize_string(output.response);
+        match field {
+            ResponseOutputType::Keywords => {
+                formatter.format_keywords(content, &self.keywords).await
+            }
+            _ => formatter.format_list(content, field).await,
+        }
+    }
+
+    /// Review a list as a comma separated string. Returns the accepted or replaced list, or
+    /// `None` when it should be regenerated.
+    fn review_list(&self, field: ResponseOutputType, list: Vec<String>) -> Option<Vec<String>> {
+        log::info!("{}: {}", field, list.join(", "));
+        match self.review(field, &list.join(", "), &[]) {
+            Review::Accept => Some(list),
+            Review::Replace(text) => Some(
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            Review::Regenerate => None,
+        }
+    }
+
+    /// Generate and format a list of values, until the review accepts or replaces it.
+    async fn generate_list(
+        &self,
+        generator: &OutputGenerator<'_>,
+        formatter: &OutputFormatter<'_>,
+        field: ResponseOutputType,
+    ) -> Result<Vec<String>, BrainiacError> {
+        loop {
+            let list = self
+                .generate_list_value(generator, formatter, field)
+                .await?;
+            if let Some(list) = self.review_list(field, list) {
+                return Ok(list);
+            }
+        }
+    }
+
+    /// The keywords of the article from the configured source, until the review accepts or
+    /// replaces them. Model keywords that fail validation, including a list that is empty after
+    /// normalizing, are replaced by the `extracted` phrases.
+    async fn generate_keywords(
+        &self,
+        generator: &OutputGenerator<'_>,
+        formatter: &OutputFormatter<'_>,
+        extracted: Vec<String>,
+    ) -> Result<Vec<String>, BrainiacError> {
+        let field = ResponseOutputType::Keywords;
+        loop {
+            let keywords = match self.keywords.source {
+                KeywordSource::Local => self.keywords.normalize(extracted.clone()),
+                KeywordSource::Model | KeywordSource::Both => {
+                    match self.generate_list_value(generator, formatter, field).await {
+                        Ok(mut keywords) => {
+                            if let KeywordSource::Both = self.keywords.source {
+                                keywords.extend(extracted.iter().cloned());
+                                keywords = self.keywords.normalize(keywords);
+                            }
+                            keywords
+                        }
+                        Err(error @ BrainiacError::ValidationError(..)) => {
+                            log::warn!("Extracting keywords locally after {}", error);
+                            self.keywords.normalize(extracted.clone())
+                        }
+                        Err(error) => return Err(error),
+                    }
+                }
+            };
+            if let Some(keywords) = self.review_list(field, keywords) {
+                return Ok(keywords);
+            }
+        }
+    }
+
+    /// Generate the title candidates and rank them, until the review accepts or replaces the
+    /// best one. Returns the title and the remaining candidates, best first.
+    async fn generate_title(
+        &self,
+        generator: &OutputGenerator<'_>,
+        formatter: &OutputFormatter<'_>,
+        terms: &[String],
+    ) -> Result<(String, Vec<String>), BrainiacError> {
+        loop {
+            let mut candidates = vec![];
+            for _ in 0..self.title_candidates {
+                candidates.push(
+                    Self::generate_value(generator, formatter, ResponseOutputType::Title).await?,
+                );
+            }
+            let mut ranked = title::rank(candidates, terms);
+            let best = ranked.remove(0);
+            match self.review(ResponseOutputType::Title, &best, &ranked) {
+                Review::Accept => return Ok((best, ranked)),
+                Review::Replace(title) => {
+                    ranked.insert(0, best);
+                    ranked.retain(|candidate| *candidate != title);
+                    return Ok((title, ranked));
+                }
+                Review::Regenerate => continue,
+            }
+        }
+    }
+
+    async fn generate_fields(&self, content: &str) -> Result<GeneratedFields, BrainiacError> {
+        let mut timings = Timings::default();
+        let start = Instant::now();
+        // Terms and keywords come from the prose, the prompt text has placeholders for code blocks
+        // and images
+        let prose = markdown::analyze(content).prose;
+        let terms = title::key_terms(&prose);
+        let extracted = keywords::extract(&prose);
+        let generator = self.generator(markdown::to_prompt_text(content)).await?;
+        timings.condense = start.elapsed();
+        let formatter = self.formatter();
+
+        let start = Instant::now();
+        let (title, alternate_titles) = self.generate_title(&generator, &formatter, &terms).await?;
+        timings
+            .fields
+            .push((ResponseOutputType::Title, start.elapsed()));
+        let start = Instant::now();
+        let description = self
+            .generate_text(&generator, &formatter, ResponseOutputType::Description)
+            .await?;
+        timings
+            .fields
+            .push((ResponseOutputType::Description, start.elapsed()));
+        let start = Instant::now();
+        let genres = self
+            .generate_list(&generator, &formatter, ResponseOutputType::Genre)
+            .await?;
+        timings
+            .fields
+            .push((ResponseOutputType::Genre, start.elapsed()));
+        let start = Instant::now();
+        let keywords = self
+            .generate_keywords(&generator, &formatter, extracted)
+            .await?;
+        timings
+            .fields
+            .push((ResponseOutputType::Keywords, start.elapsed()));
+
+        Ok(GeneratedFields {
+            title,
+            alternate_titles,
+            description,
+            genres,
+            keywords,
+            timings,
+        })
+    }
+
+    /// Render `metadata` as front matter and replace the existing front matter of `content`.
+    fn render(
+        &self,
+        metadata: Metadata,
+        content: &str,
+        timings: Timings,
+    ) -> Result<GeneratedDocument, BrainiacError> {
+        let rendered_metadata = generate_article_matter(&metadata)?;
+        let document = format!(
+            "{}\n{}",
+            rendered_metadata,
+            markdown::strip_front_matter(content)
+        );
+        Ok(GeneratedDocument {
+            metadata,
+            rendered_metadata,
+            document,
+            gen_model: self.gen_model.clone(),
+            format_model: self.format_model.clone(),
+            timings,
+            genre_matches: vec![],
+            unmatched_genres: vec![],
+        })
+    }
+
+    async fn generate_document(
+        &self,
+        content: &str,
+        dates: history::ArticleDates,
+    ) -> Result<GeneratedDocument, BrainiacError> {
+        let start = Instant::now();
+        let mut fields = self.generate_fields(content).await?;
+
+        let (genre_matches, unmatched_genres) = self.classify_genres(&fields.genres);
+        let metadata = Metadata {
+            slug: slugify!(fields.title.as_str()),
+            title: fields.title,
+            alternate_titles: fields.alternate_titles,
+            description: fields.description,
+            author: self.author.clone(),
+            analytics: AnalyticsMetadata {
+                created_at: dates.created_at,
+                updated_at: dates.updated_at,
+                ..get_analytics_data(content, self.readability)
+            },
+            interest: InterestMetadata {
+                keywords: fields.keywords,
+                genres: genre_matches
+                    .iter()
+                    .map(|matched| matched.genre.clone())
+                    .collect(),
+                ..Default::default()
+            },
+            toc: markdown::extract_toc(content),
+        };
+        fields.timings.total = start.elapsed();
+        let mut generated = self.render(metadata, content, fields.timings)?;
+        generated.genre_matches = genre_matches;
+        generated.unmatched_genres = unmatched_genres;
+        Ok(generated)
+    }
+
+    /// Resolve generated genre labels against the taxonomy, warning about the ones that match
+    /// no genre or match with a low confidence. Returns the matches and the unmatched labels.
+    fn classify_genres(&self, labels: &[String]) -> (Vec<GenreMatch>, Vec<String>) {
+        let (matches, unmatched) = self.taxonomy.classify(labels);
+        for matched in &matches {
+            if matched.confidence < LOW_CONFIDENCE {
+                log::warn!(
+                    "The genre {} was matched to {} with a low confidence of {}",
+                    matched.label,
+                    matched.genre,
+                    matched.confidence
+                );
+            }
+        }
+        for label in &unmatched {
+            log::warn!("The genre {} is not in the taxonomy", label);
+        }
+        (matches, unmatched)
+    }
+
+    /// Regenerate a single field of the front matter of `content`, keeping the other fields.
+    /// Content without front matter has all of its fields generated.
+    pub async fn regenerate_field(
+        &self,
+        content: &str,
+        field: ResponseOutputType,
+    ) -> Result<GeneratedDocument, BrainiacError> {
+        let Some(parsed) = parse_article_matter(content, None)? else {
+            return self.generate_metadata(content).await;
+        };
+        let mut metadata = parsed.data;
+        let mut timings = Timings::default();
+        let start = Instant::now();
+        let prose = markdown::analyze(content).prose;
+        let terms = title::key_terms(&prose);
+        let extracted = keywords::extract(&prose);
+        let generator = self.generator(markdown::to_prompt_text(content)).await?;
+        timings.condense = start.elapsed();
+        let formatter = self.formatter();
+
+        let field_start = Instant::now();
+        let mut genre_matches = vec![];
+        let mut unmatched_genres = vec![];
+        match field {
+            ResponseOutputType::Title => {
+                (metadata.title, metadata.alternate_titles) =
+                    self.generate_title(&generator, &formatter, &terms).await?;
+                metadata.slug = slugify!(metadata.title.as_str());
+            }
+            ResponseOutputType::Description => {
+                metadata.description = self.generate_text(&generator, &formatter, field).await?;
+            }
+            ResponseOutputType::Genre => {
+                let labels = self.generate_list(&generator, &formatter, field).await?;
+                (genre_matches, unmatched_genres) = self.classify_genres(&labels);
+                metadata.interest.genres = genre_matches
+                    .iter()
+                    .map(|matched| matched.genre.clone())
+                    .collect();
+            }
+            ResponseOutputType::Keywords => {
+                metadata.interest.keywords = self
+                    .generate_keywords(&generator, &formatter, extracted)
+                    .await?;
+            }
+        }
+        timings.fields.push((field, field_start.elapsed()));
+        timings.total = start.elapsed();
+        let mut generated = self.render(metadata, content, timings)?;
+        generated.genre_matches = genre_matches;
+        generated.unmatched_genres = unmatched_genres;
+        Ok(generated)
+    }
+
+    /// Generate metadata for article content held in memory.
+    /// This method has no file system side effects, the dates come from existing front matter or
+    /// default to today.
+    pub async fn generate_metadata(
+        &self,
+        content: &str,
+    ) -> Result<GeneratedDocument, BrainiacError> {
+        self.generate_document(content, history::article_dates(None, content))
+            .await
+    }
+
+    /// Append metadata to an article.
+    /// This method has the following side effects:
+    /// - Reads the article from the `source` file or stdin
+    /// - Generates metadata fields based on the model and article content
+    /// - Writes the article to the `output` target, unless `dry_run` is set
+    /// - Copies a replaced file to the client backup
+    pub async fn append(
+        &self,
+        source: &InputSource,
+        output: &OutputTarget,
+        dry_run: bool,
+    ) -> Result<BrainiacAppendOutput, BrainiacError> {
+        let article_content = source.read()?;
+        let source_path = source.path();
+        if source_path.is_none() && matches!(output, OutputTarget::InPlace) {
+            return Err(BrainiacError::ConfigError(
+                None,
+                "an article read from stdin can not be updated in place".to_string(),
+            ));
+        }
+        let dates = history::article_dates(source_path, &article_content);
+        let generated = self
+            .generate_document(&article_content, dates)
+            .await
+            .map_err(|error| match source_path {
+                Some(path) => error.with_path(path),
+                None => error,
+            })?;
+
+        let (output_path, status) = match (output, source_path) {
+            (OutputTarget::InPlace, Some(source_path)) => {
+                (Some(source_path.to_path_buf()), WriteStatus::Overwritten)
+            }
+            (OutputTarget::Directory(directory), _) => {
+                let output_path = directory.join(create_output_file_name(&generated.metadata.slug));
+                let (output_path, status) =
+                    output::resolve_output_path(output_path, self.on_collision)?;
+                (Some(output_path), status)
+            }
+            _ => (None, WriteStatus::Streamed),
+        };
+        let status = if dry_run && status != WriteStatus::Streamed {
+            WriteStatus::DryRun
+        } else {
+            status
+        };
+        if let Some(output_path) = &output_path {
+            match status {
+                WriteStatus::Skipped => {
+                    log::warn!("Skipped existing file {}", output_path.display())
+                }
+                _ => output::write_output(
+                    output_path,
+                    &generated.document,
+                    status,
+                    self.backup.as_ref(),
+                )?,
+            }
+        }
+
+        Ok(BrainiacAppendOutput {
+            generated,
+            output_path,
+            status,
+        })
+    }
+
+    /// Parse metadata from article content held in memory.
+    /// Returns the metadata and content, or `None` when the content has no brainiac front matter.
+    pub fn parse(
+        &self,
+        content: &str,
+    ) -> Result<Option<ParsedEntityStruct<Metadata>>, BrainiacError> {
+        parse_article_matter(content, None)
+    }
+
+    /// Check the front matter of article content held in memory against its body.
+    pub fn lint(&self, content: &str) -> Result<Vec<LintIssue>, BrainiacError> {
+        lint::lint(content, &self.taxonomy)
+    }
+}
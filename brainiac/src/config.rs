@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{BrainiacError, FileOperation};
+use crate::keywords::KeywordSettings;
+use crate::taxonomy::{Genre, Taxonomy};
+
+/// Project wide settings, usually read from a `brainiac.toml` file
+#[derive(Clone, Default, Deserialize)]
+pub struct Config {
+    /// The genre taxonomy, the `ArticleGenre` variants are used when it is empty
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    /// Labels the model uses and the genre they stand for, such as `"Sci-Fi" = "Entertainment"`
+    #[serde(default)]
+    pub genre_synonyms: HashMap<String, String>,
+    /// How generated keywords are cleaned up and the tag vocabulary they are mapped onto
+    #[serde(default)]
+    pub keywords: KeywordSettings,
+}
+
+fn has_empty_name(genres: &[Genre]) -> bool {
+    genres
+        .iter()
+        .any(|genre| genre.name.trim().is_empty() || has_empty_name(&genre.children))
+}
+
+impl Config {
+    /// Read the config from the TOML file at `path`.
+    pub fn load(path: &Path) -> Result<Self, BrainiacError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(BrainiacError::file(FileOperation::Read, path))?;
+        let config = toml::from_str::<Config>(&content).map_err(|error| {
+            BrainiacError::ConfigError(Some(path.to_path_buf()), error.message().to_string())
+        })?;
+        if has_empty_name(&config.genres) {
+            return Err(BrainiacError::ConfigError(
+                Some(path.to_path_buf()),
+                "every genre needs a name".to_string(),
+            ));
+        }
+        let taxonomy = config.taxonomy();
+        let unknown = config
+            .genre_synonyms
+            .iter()
+            .find(|(_, genre)| !taxonomy.contains(genre));
+        if let Some((label, genre)) = unknown {
+            return Err(BrainiacError::ConfigError(
+                Some(path.to_path_buf()),
+                format!(
+                    "the synonym {} stands for the genre {}, which is not in the taxonomy",
+                    label, genre
+                ),
+            ));
+        }
+        if config.keywords.max == 0 {
+            return Err(BrainiacError::ConfigError(
+                Some(path.to_path_buf()),
+                "keywords.max must be greater than 0".to_string(),
+            ));
+        }
+        if config.keywords.vocabulary_only && config.keywords.vocabulary.is_empty() {
+            return Err(BrainiacError::ConfigError(
+                Some(path.to_path_buf()),
+                "keywords.vocabulary_only needs a keywords.vocabulary".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+
+    pub fn taxonomy(&self) -> Taxonomy {
+        let taxonomy = match self.genres.is_empty() {
+            true => Taxonomy::default(),
+            false => Taxonomy::new(self.genres.clone()),
+        };
+        taxonomy.with_synonyms(&self.genre_synonyms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(content: &str) -> Result<Config, BrainiacError> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+        Config::load(file.path())
+    }
+
+    fn config_error(content: &str) -> String {
+        match load(content) {
+            Err(BrainiacError::ConfigError(_, message)) => message,
+            _ => panic!("expected a config error"),
+        }
+    }
+
+    #[test]
+    fn keyword_settings_default_when_missing() {
+        let config = load("").unwrap();
+        assert_eq!(config.keywords.max, 10);
+        assert!(!config.keywords.vocabulary_only);
+    }
+
+    #[test]
+    fn keywords_max_must_be_positive() {
+        assert_eq!(
+            config_error("[keywords]\nmax = 0\n"),
+            "keywords.max must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn vocabulary_only_needs_a_vocabulary() {
+        assert_eq!(
+            config_error("[keywords]\nvocabulary_only = true\n"),
+            "keywords.vocabulary_only needs a keywords.vocabulary"
+        );
+        let config = load("[keywords]\nvocabulary_only = true\nvocabulary = [\"Rust\"]\n").unwrap();
+        assert_eq!(config.keywords.vocabulary, ["Rust"]);
+    }
+}
@@ -1,16 +1,95 @@
 use std::{
     error::Error,
     fmt::{Debug, Display},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use ollama_rs::error::OllamaError;
 
+use crate::model::ResponseOutputType;
+
+/// The file system operation that failed
+#[derive(Debug, Clone, Copy, strum::Display)]
+pub enum FileOperation {
+    Read,
+    Write,
+    Backup,
+    Restore,
+}
+
+/// Describes the model request that an error happened in
+#[derive(Debug, Clone, Default)]
+pub struct ModelContext {
+    pub model: String,
+    pub field: Option<ResponseOutputType>,
+    pub prompt: Option<String>,
+    /// The article the request was made for
+    pub path: Option<PathBuf>,
+}
+
+impl Display for ModelContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\nModel: {}", self.model)?;
+        if let Some(field) = &self.field {
+            write!(f, "\nField: {}", field)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "\nFile: {}", path.display())?;
+        }
+        if let Some(prompt) = &self.prompt {
+            write!(f, "\nPrompt: {}", prompt)?;
+        }
+        Ok(())
+    }
+}
+
+/// Broad groups of errors, used by callers to decide how to react to a failure
+#[derive(Debug, Clone, Copy, PartialEq, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The model backend failed or could not be reached
+    Model,
+    /// Reading or writing a file failed
+    Io,
+    /// Existing front matter could not be read
+    FrontMatter,
+    /// The model responded with output that could not be used
+    Validation,
+    /// The provided options or configuration are invalid
+    Config,
+    /// The output file already exists
+    Collision,
+    /// A bug or unexpected state inside brainiac
+    Internal,
+}
+
 #[derive(Debug)]
 pub enum BrainiacError {
     OllamaError(OllamaError),
-    /// OllamaError with a message and optional prompt that triggered
-    OllamaDetailedError(OllamaError, Option<String>),
+    /// OllamaError with the model request that triggered it
+    OllamaDetailedError(OllamaError, ModelContext),
     IoError(std::io::Error),
+    /// IoError with the operation and the path of the file it was performed on
+    FileError(FileOperation, PathBuf, std::io::Error),
+    /// Metadata could not be rendered as front matter
+    SerializeError(toml::ser::Error),
+    /// A prompt or response could not be converted to or from JSON
+    JsonError(serde_json::Error),
+    /// The generator was asked for output before its content was set
+    ContentNotSet,
+    /// The output file already exists and the collision policy does not allow replacing it
+    OutputCollision(PathBuf),
+    /// The front matter is not valid TOML, with the file when there is one
+    FrontMatterParseError(Option<PathBuf>, String),
+    /// The front matter is valid TOML but does not match the `Metadata` schema
+    SchemaMismatch(Option<PathBuf>, String),
+    /// The model output for a field could not be validated
+    ValidationError(ModelContext, String),
+    /// An invalid option or configuration value, with the config file when there is one
+    ConfigError(Option<PathBuf>, String),
+    /// The model did not finish generating within the time limit
+    TimeoutError(Duration),
 }
 
 impl From<OllamaError> for BrainiacError {
@@ -19,6 +98,56 @@ impl From<OllamaError> for BrainiacError {
     }
 }
 
+impl BrainiacError {
+    /// Map an io error on the file at `path` to a `FileError`.
+    pub fn file(operation: FileOperation, path: &Path) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.to_path_buf();
+        move |error| BrainiacError::FileError(operation, path, error)
+    }
+
+    /// Attach the article path to errors that carry a `ModelContext`.
+    pub fn with_path(mut self, path: &Path) -> Self {
+        match &mut self {
+            BrainiacError::OllamaDetailedError(_, context)
+            | BrainiacError::ValidationError(context, _) => {
+                context.path = Some(path.to_path_buf());
+            }
+            _ => {}
+        }
+        self
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            BrainiacError::OllamaError(_)
+            | BrainiacError::OllamaDetailedError(..)
+            | BrainiacError::TimeoutError(_) => ErrorCategory::Model,
+            BrainiacError::IoError(_) | BrainiacError::FileError(..) => ErrorCategory::Io,
+            BrainiacError::FrontMatterParseError(..) | BrainiacError::SchemaMismatch(..) => {
+                ErrorCategory::FrontMatter
+            }
+            BrainiacError::ValidationError(..) => ErrorCategory::Validation,
+            BrainiacError::ConfigError(..) => ErrorCategory::Config,
+            BrainiacError::OutputCollision(_) => ErrorCategory::Collision,
+            BrainiacError::SerializeError(_)
+            | BrainiacError::JsonError(_)
+            | BrainiacError::ContentNotSet => ErrorCategory::Internal,
+        }
+    }
+}
+
+impl From<toml::ser::Error> for BrainiacError {
+    fn from(value: toml::ser::Error) -> Self {
+        BrainiacError::SerializeError(value)
+    }
+}
+
+impl From<serde_json::Error> for BrainiacError {
+    fn from(value: serde_json::Error) -> Self {
+        BrainiacError::JsonError(value)
+    }
+}
+
 impl From<std::io::Error> for BrainiacError {
     fn from(value: std::io::Error) -> Self {
         BrainiacError::IoError(value)
@@ -40,16 +169,49 @@ impl Display for BrainiacError {
                 };
                 write!(f, "OllamaError: {}", message)
             }
-            BrainiacError::OllamaDetailedError(ollama_error, prompt) => {
-                let mut e = format!("OllamaError: {}", ollama_error);
-                if let Some(prompt) = prompt {
-                    e.push_str(&format!("\nPrompt: {}", prompt));
-                }
-                write!(f, "{}", e)
+            BrainiacError::OllamaDetailedError(ollama_error, context) => {
+                write!(f, "OllamaError: {}{}", ollama_error, context)
             }
             BrainiacError::IoError(error) => {
                 write!(f, "IoError: {}", error)
             }
+            BrainiacError::FileError(operation, path, error) => {
+                write!(f, "FileError: {} {}: {}", operation, path.display(), error)
+            }
+            BrainiacError::SerializeError(error) => {
+                write!(f, "SerializeError: {}", error)
+            }
+            BrainiacError::JsonError(error) => {
+                write!(f, "JsonError: {}", error)
+            }
+            BrainiacError::ContentNotSet => {
+                write!(f, "ContentNotSet: the article content was not provided")
+            }
+            BrainiacError::OutputCollision(path) => {
+                write!(f, "OutputCollision: {} already exists", path.display())
+            }
+            BrainiacError::FrontMatterParseError(path, message) => match path {
+                Some(path) => write!(f, "FrontMatterParseError: {}: {}", path.display(), message),
+                None => write!(f, "FrontMatterParseError: {}", message),
+            },
+            BrainiacError::SchemaMismatch(path, message) => match path {
+                Some(path) => write!(f, "SchemaMismatch: {}: {}", path.display(), message),
+                None => write!(f, "SchemaMismatch: {}", message),
+            },
+            BrainiacError::ValidationError(context, message) => {
+                write!(f, "ValidationError: {}{}", message, context)
+            }
+            BrainiacError::ConfigError(path, message) => match path {
+                Some(path) => write!(f, "ConfigError: {}: {}", path.display(), message),
+                None => write!(f, "ConfigError: {}", message),
+            },
+            BrainiacError::TimeoutError(timeout) => {
+                write!(
+                    f,
+                    "TimeoutError: no response within {} seconds",
+                    timeout.as_secs()
+                )
+            }
         }
     }
 }
@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::process::Command;
+
+use chrono::NaiveDate;
+
+pub struct ArticleDates {
+    pub created_at: NaiveDate,
+    pub updated_at: NaiveDate,
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Read `created_at` from existing TOML front matter, either at the top level or in the
+/// `analytics` table written by brainiac.
+fn front_matter_created_at(content: &str) -> Option<NaiveDate> {
+    let matter = gray_matter::Matter::<gray_matter::engine::TOML>::new();
+    let front_matter = matter.parse(content).matter;
+    let table = toml::from_str::<toml::Table>(&front_matter).ok()?;
+    let value = table
+        .get("analytics")
+        .and_then(|analytics| analytics.get("created_at"))
+        .or_else(|| table.get("created_at"))
+        .or_else(|| table.get("date"))?;
+    match value {
+        toml::Value::Datetime(datetime) => parse_date(&datetime.to_string()),
+        toml::Value::String(date) => parse_date(date),
+        _ => None,
+    }
+}
+
+fn git_command(path: &Path) -> Command {
+    let directory = path.parent().filter(|p| !p.as_os_str().is_empty());
+    let mut command = Command::new("git");
+    command.current_dir(directory.unwrap_or(Path::new(".")));
+    command
+}
+
+/// The dates of the first and last commits touching `path`, oldest first.
+fn git_commit_dates(path: &Path) -> Option<(NaiveDate, NaiveDate)> {
+    let output = git_command(path)
+        .args(["log", "--follow", "--format=%cs", "--"])
+        .arg(path.file_name()?)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let dates = stdout
+        .lines()
+        .filter_map(parse_date)
+        .collect::<Vec<NaiveDate>>();
+    Some((*dates.last()?, *dates.first()?))
+}
+
+/// Whether `path` has changes that are not committed yet.
+fn git_has_changes(path: &Path) -> bool {
+    let Some(file_name) = path.file_name() else {
+        return false;
+    };
+    git_command(path)
+        .args(["diff", "--quiet", "HEAD", "--"])
+        .arg(file_name)
+        .status()
+        .is_ok_and(|status| status.code() == Some(1))
+}
+
+fn file_system_dates(path: &Path) -> Option<(NaiveDate, NaiveDate)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?;
+    let created = metadata.created().unwrap_or(modified);
+    let to_date = |time| chrono::DateTime::<chrono::Local>::from(time).date_naive();
+    Some((to_date(created), to_date(modified)))
+}
+
+/// Resolve the creation and update dates of the article at `path`, or of content without a file.
+/// `created_at` is taken from the existing front matter, then the first git commit of the file,
+/// then the file system. `updated_at` is the last git commit of the file, or the modification
+/// time when the file has uncommitted changes or is not tracked by git.
+pub fn article_dates(path: Option<&Path>, content: &str) -> ArticleDates {
+    let today = chrono::Utc::now().date_naive();
+    let git = path.and_then(git_commit_dates);
+    let file_system = path.and_then(file_system_dates);
+
+    let created_at = front_matter_created_at(content)
+        .or(git.map(|(first, _)| first))
+        .or(file_system.map(|(created, _)| created))
+        .unwrap_or(today);
+    let updated_at = match git {
+        Some((_, last)) if !path.is_some_and(git_has_changes) => Some(last),
+        _ => file_system.map(|(_, modified)| modified),
+    }
+    .unwrap_or(today)
+    .max(created_at);
+
+    ArticleDates {
+        created_at,
+        updated_at,
+    }
+}
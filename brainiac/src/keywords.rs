@@ -0,0 +1,528 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::ollama::response;
+use crate::{stop_words, taxonomy};
+
+/// The maximum number of keywords kept when none is configured.
+const DEFAULT_MAX_KEYWORDS: usize = 10;
+/// Keywords at least this similar to a vocabulary tag are replaced by the tag, and keywords at
+/// least this similar to a kept keyword are dropped as near duplicates.
+const MIN_VOCABULARY_SIMILARITY: f64 = 0.85;
+/// Candidate phrases longer than this are dropped, they read like sentences rather than keywords.
+const MAX_PHRASE_WORDS: usize = 3;
+/// Common verbs and adverbs, they rarely belong in a keyword.
+const FILLER_WORDS: [&str; 40] = [
+    "although",
+    "contain",
+    "contains",
+    "help",
+    "helps",
+    "however",
+    "include",
+    "includes",
+    "including",
+    "like",
+    "make",
+    "makes",
+    "many",
+    "might",
+    "much",
+    "must",
+    "offer",
+    "offers",
+    "often",
+    "overall",
+    "play",
+    "plays",
+    "provide",
+    "provides",
+    "show",
+    "showing",
+    "shows",
+    "similar",
+    "suggest",
+    "suggests",
+    "support",
+    "supporting",
+    "supports",
+    "though",
+    "very",
+    "well",
+    "where",
+    "which",
+    "while",
+    "would",
+];
+/// Words ending in `s` that are not plurals.
+const SINGULAR_WORDS: [&str; 10] = [
+    "aws",
+    "chaos",
+    "diabetes",
+    "dns",
+    "kubernetes",
+    "lens",
+    "news",
+    "series",
+    "species",
+    "windows",
+];
+/// Plurals ending in `ies` that keep the `ie`, like `movies` and `cookies`.
+const IE_PLURAL_ENDINGS: [&str; 3] = ["vies", "kies", "zies"];
+/// Plurals ending in `ies` that keep the `ie` but have no telling ending.
+const IE_PLURALS: [&str; 5] = ["calories", "brownies", "smoothies", "selfies", "zombies"];
+/// Plurals ending in `as`, words like `bias` and `atlas` are singular.
+const AS_PLURALS: [&str; 10] = [
+    "areas", "bananas", "cameras", "dramas", "formulas", "ideas", "personas", "pizzas", "quotas",
+    "schemas",
+];
+/// Plural endings that add `es` to the singular, like `boxes` and `matches`.
+const ES_PLURAL_ENDINGS: [&str; 4] = ["ches", "shes", "sses", "xes"];
+/// Plural endings that add `s` to a singular ending in `e`, like `stones` and `recipes`.
+/// Other words ending in `es`, like `kubernetes`, are left alone.
+const E_PLURAL_ENDINGS: [&str; 20] = [
+    "ages", "ales", "ames", "ates", "ices", "ides", "ines", "ipes", "ites", "ives", "odes", "ones",
+    "ores", "otes", "ses", "ues", "ules", "ures", "ytes", "zes",
+];
+
+/// Where keywords come from
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordSource {
+    /// Generate keywords with the model, falling back to local extraction when the model output
+    /// fails validation
+    #[default]
+    Model,
+    /// Extract keywords from the article without the model
+    Local,
+    /// Generate keywords with the model and add the locally extracted ones, up to the maximum
+    Both,
+}
+
+/// How generated keywords are capitalized
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCase {
+    #[default]
+    Lower,
+    Title,
+}
+
+/// How generated keywords are cleaned up, read from the `[keywords]` table of the config
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct KeywordSettings {
+    pub source: KeywordSource,
+    pub case: KeywordCase,
+    /// The maximum number of keywords, the first ones are kept
+    pub max: usize,
+    /// The project wide tags keywords are mapped onto
+    pub vocabulary: Vec<String>,
+    /// Drop keywords that match no vocabulary tag instead of keeping them as they are
+    pub vocabulary_only: bool,
+}
+
+impl Default for KeywordSettings {
+    fn default() -> Self {
+        Self {
+            source: KeywordSource::default(),
+            case: KeywordCase::default(),
+            max: DEFAULT_MAX_KEYWORDS,
+            vocabulary: vec![],
+            vocabulary_only: false,
+        }
+    }
+}
+
+/// Split the items of a generated list that hold several keywords, such as a comma separated
+/// string or a JSON response nested in a string.
+fn split(keywords: Vec<String>) -> Vec<String> {
+    keywords
+        .into_iter()
+        .flat_map(|item| match response::parse_list(&item) {
+            Ok(items) => items,
+            Err(_) => vec![item],
+        })
+        .flat_map(|item| {
+            item.split([',', ';', '\n'])
+                .map(str::to_string)
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+/// The singular of an English word, for the common plural endings.
+/// Words that are not clearly plural are returned unchanged.
+fn singularize(word: &str) -> String {
+    let ends_with_any = |endings: &[&str]| endings.iter().any(|end| word.ends_with(end));
+    let without = |count: usize| word[..word.len() - count].to_string();
+    if word.chars().count() <= 3 || SINGULAR_WORDS.contains(&word) {
+        word.to_string()
+    } else if word.ends_with("ies") {
+        match ends_with_any(&IE_PLURAL_ENDINGS) || IE_PLURALS.contains(&word) {
+            true => without(1),
+            false => format!("{}y", without(3)),
+        }
+    } else if word.ends_with("as") {
+        match AS_PLURALS.contains(&word) {
+            true => without(1),
+            false => word.to_string(),
+        }
+    } else if ends_with_any(&ES_PLURAL_ENDINGS) {
+        without(2)
+    } else if word.ends_with("es") {
+        match ends_with_any(&E_PLURAL_ENDINGS) {
+            true => without(1),
+            false => word.to_string(),
+        }
+    } else if word.ends_with('s') && !ends_with_any(&["ss", "us", "is", "ics", "'s"]) {
+        without(1)
+    } else {
+        word.to_string()
+    }
+}
+
+/// Lowercase `keyword`, strip punctuation around its words and make its last word singular.
+fn clean(keyword: &str) -> String {
+    let mut words = keyword
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<String>>();
+    if let Some(last) = words.last_mut() {
+        *last = singularize(last);
+    }
+    words.join(" ")
+}
+
+fn title_case(keyword: &str) -> String {
+    keyword
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn is_stop_word(word: &str) -> bool {
+    stop_words::is_stop_word(word) || FILLER_WORDS.contains(&word)
+}
+
+/// Extract the key phrases of `content` with RAKE (rapid automatic keyword extraction), best
+/// first. Phrases are the runs of words between stop words and punctuation, scored by the sum of
+/// the degree to frequency ratio of their words.
+pub fn extract(content: &str) -> Vec<String> {
+    let mut phrases: Vec<Vec<String>> = vec![];
+    for fragment in content
+        .split(|c: char| !(c.is_alphanumeric() || c.is_whitespace() || c == '\'' || c == '-'))
+    {
+        let mut phrase: Vec<String> = vec![];
+        for word in fragment.split_whitespace() {
+            let word = word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            let separates =
+                word.is_empty() || is_stop_word(&word) || word.chars().all(|c| c.is_numeric());
+            if separates {
+                phrases.push(std::mem::take(&mut phrase));
+            }
+            if !separates {
+                phrase.push(word);
+            }
+        }
+        phrases.push(phrase);
+    }
+    phrases.retain(|phrase| !phrase.is_empty() && phrase.len() <= MAX_PHRASE_WORDS);
+
+    let mut frequency: HashMap<&str, usize> = HashMap::new();
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequency.entry(word).or_default() += 1;
+            *degree.entry(word).or_default() += phrase.len();
+        }
+    }
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut scored: Vec<(String, f64)> = vec![];
+    for phrase in &phrases {
+        let text = phrase.join(" ");
+        if !seen.insert(text.clone()) {
+            continue;
+        }
+        let score = phrase
+            .iter()
+            .map(|word| degree[word.as_str()] as f64 / frequency[word.as_str()] as f64)
+            .sum::<f64>();
+        // Plain RAKE favours long phrases that occur once, weigh in how often the words recur
+        let recurrence = phrase
+            .iter()
+            .map(|word| frequency[word.as_str()] as f64)
+            .sum::<f64>()
+            / phrase.len() as f64;
+        scored.push((text, score * recurrence));
+    }
+    // The sort is stable, so equally scored phrases keep their order in the article
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.into_iter().map(|(phrase, _)| phrase).collect()
+}
+
+impl KeywordSettings {
+    /// The vocabulary tag closest to a cleaned `keyword`, if any is close enough.
+    fn map(&self, keyword: &str) -> Option<String> {
+        self.vocabulary
+            .iter()
+            .map(|tag| (tag, taxonomy::similarity(&clean(tag), keyword)))
+            .filter(|(_, similarity)| *similarity >= MIN_VOCABULARY_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(tag, _)| tag.clone())
+    }
+
+    /// Split generated keywords into single items, clean them up, map them onto the vocabulary
+    /// and remove duplicates and near duplicates, keeping at most `max` in their generated order.
+    pub fn normalize(&self, keywords: Vec<String>) -> Vec<String> {
+        let mut normalized: Vec<String> = vec![];
+        // The cleaned form of every kept keyword, to compare new keywords against
+        let mut kept: Vec<String> = vec![];
+        for item in split(keywords) {
+            let keyword = clean(&item);
+            if keyword.is_empty() {
+                continue;
+            }
+            let keyword = match self.map(&keyword) {
+                Some(tag) => tag,
+                None if self.vocabulary_only => {
+                    log::debug!("Dropped the keyword {} that is not in the vocabulary", item);
+                    continue;
+                }
+                None => match self.case {
+                    KeywordCase::Lower => keyword,
+                    KeywordCase::Title => title_case(&keyword),
+                },
+            };
+            let cleaned = clean(&keyword);
+            let duplicate = kept
+                .iter()
+                .find(|kept| taxonomy::similarity(kept, &cleaned) >= MIN_VOCABULARY_SIMILARITY);
+            match duplicate {
+                Some(duplicate) => {
+                    log::debug!("Dropped the keyword {} close to {}", item, duplicate)
+                }
+                None => {
+                    kept.push(cleaned);
+                    normalized.push(keyword);
+                }
+            }
+        }
+        normalized.truncate(self.max);
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    /// The keywords of the README example, a JSON response stored as a single keyword
+    const README_KEYWORDS: &str = r#"{"response":["digested health", "fiber", "brat diet", "electrolytes", "inflammatory bowel disease", "crohn's and colitis foundation", "trypheoforbidean", "memory preservation", "mood", "potassium", "kidney stones", "supplements", "banana nutrition"]}"#;
+
+    #[test]
+    fn extract_ranks_recurring_phrases_first() {
+        let content = "Kidney stones are painful. Kidney stones, sadly, hurt. \
+            Bananas contain potassium. The brat diet includes bananas.";
+        let phrases = extract(content);
+        assert_eq!(phrases[0], "kidney stones");
+        assert!(phrases.contains(&"potassium".to_string()));
+        assert!(phrases.contains(&"brat diet".to_string()));
+    }
+
+    #[test]
+    fn extract_splits_on_stop_words_punctuation_and_numbers() {
+        assert_eq!(
+            extract("Fiber and potassium, 42 bananas"),
+            strings(&["fiber", "potassium", "bananas"])
+        );
+    }
+
+    #[test]
+    fn extract_drops_long_phrases_and_duplicates() {
+        let phrases = extract("Slow cooked tomato bean soup. Tomato soup. Tomato soup.");
+        assert_eq!(phrases, strings(&["tomato soup"]));
+    }
+
+    #[test]
+    fn extract_from_prose_skips_code_block_and_image_placeholders() {
+        let article = "The lexer emits tokens.\n\n```rust\nlet lexer = Lexer::new();\n```\n\n\
+            ![Parser diagram](parser.png)\n\n```toml\nname = \"lexer\"\n```\n\nThe lexer emits tokens.\n";
+        let placeholders = ["rust block", "toml block", "line", "lines", "image"];
+        let prompt_phrases = extract(&crate::markdown::to_prompt_text(article));
+        assert!(placeholders
+            .iter()
+            .any(|placeholder| prompt_phrases.contains(&placeholder.to_string())));
+
+        let phrases = extract(&crate::markdown::analyze(article).prose);
+        assert_eq!(phrases, strings(&["lexer emits tokens", "parser diagram"]));
+    }
+
+    #[test]
+    fn split_unpacks_json_responses_and_separated_lists() {
+        assert_eq!(
+            split(strings(&[r#"{"response":["a", "b"]}"#, "c, d; e\nf", "g"])),
+            strings(&["a", "b", "c", " d", " e", "f", "g"])
+        );
+        assert_eq!(
+            split(strings(&[r#"{"response":"a, b"}"#])),
+            strings(&["a", " b"])
+        );
+    }
+
+    #[test]
+    fn singularize_handles_common_plurals() {
+        for (plural, singular) in [
+            ("stories", "story"),
+            ("movies", "movie"),
+            ("cookies", "cookie"),
+            ("calories", "calorie"),
+            ("bananas", "banana"),
+            ("boxes", "box"),
+            ("matches", "match"),
+            ("classes", "class"),
+            ("stones", "stone"),
+            ("recipes", "recipe"),
+            ("diseases", "disease"),
+            ("electrolytes", "electrolyte"),
+            ("supplements", "supplement"),
+            ("keywords", "keyword"),
+        ] {
+            assert_eq!(singularize(plural), singular);
+        }
+    }
+
+    #[test]
+    fn singularize_leaves_singular_words_alone() {
+        for word in [
+            "bias",
+            "atlas",
+            "kubernetes",
+            "windows",
+            "news",
+            "series",
+            "physics",
+            "status",
+            "analysis",
+            "glass",
+            "gas",
+            "crohn's",
+        ] {
+            assert_eq!(singularize(word), word);
+        }
+    }
+
+    #[test]
+    fn clean_strips_punctuation_and_singularizes_the_last_word() {
+        assert_eq!(clean("  \"Kidney   Stones.\" "), "kidney stone");
+        assert_eq!(
+            clean("Crohn's and Colitis Foundation"),
+            "crohn's and colitis foundation"
+        );
+        assert_eq!(clean("..."), "");
+    }
+
+    #[test]
+    fn normalize_cleans_the_readme_keywords() {
+        let keywords = KeywordSettings::default().normalize(strings(&[README_KEYWORDS]));
+        assert_eq!(
+            keywords,
+            strings(&[
+                "digested health",
+                "fiber",
+                "brat diet",
+                "electrolyte",
+                "inflammatory bowel disease",
+                "crohn's and colitis foundation",
+                "trypheoforbidean",
+                "memory preservation",
+                "mood",
+                "potassium",
+            ])
+        );
+    }
+
+    #[test]
+    fn normalize_removes_duplicates_and_caps_the_count() {
+        let settings = KeywordSettings {
+            max: 3,
+            ..Default::default()
+        };
+        let keywords = settings.normalize(strings(&[
+            "Bananas, banana",
+            "Fiber.",
+            "fiber",
+            "Mood, Sleep",
+        ]));
+        assert_eq!(keywords, strings(&["banana", "fiber", "mood"]));
+    }
+
+    #[test]
+    fn normalize_removes_near_duplicates() {
+        let keywords = KeywordSettings::default().normalize(strings(&[
+            "potassium, kidney stones",
+            "Potasium",
+            "kidney stone",
+            "fiber, fibre, diet",
+        ]));
+        assert_eq!(
+            keywords,
+            strings(&["potassium", "kidney stone", "fiber", "fibre", "diet"])
+        );
+    }
+
+    #[test]
+    fn normalize_title_cases_keywords() {
+        let settings = KeywordSettings {
+            case: KeywordCase::Title,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.normalize(strings(&["kidney stones", "BRAT diet"])),
+            strings(&["Kidney Stone", "Brat Diet"])
+        );
+    }
+
+    #[test]
+    fn normalize_maps_keywords_onto_the_vocabulary() {
+        let settings = KeywordSettings {
+            vocabulary: strings(&["Kidney Stones", "Potassium"]),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.normalize(strings(&["kidney stone", "potasium", "mood"])),
+            strings(&["Kidney Stones", "Potassium", "mood"])
+        );
+    }
+
+    #[test]
+    fn normalize_drops_keywords_outside_the_vocabulary_when_asked() {
+        let settings = KeywordSettings {
+            vocabulary: strings(&["Kidney Stones", "Potassium"]),
+            vocabulary_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.normalize(strings(&[README_KEYWORDS])),
+            strings(&["Potassium", "Kidney Stones"])
+        );
+    }
+}
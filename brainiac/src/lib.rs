@@ -1,40 +1,112 @@
-use std::io::Write;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
+use std::time::Duration;
 
-use error::BrainiacError;
+pub use client::{Brainiac, BrainiacBuilder, Review, DEFAULT_FORMAT_MODEL, DEFAULT_GEN_MODEL};
+use error::{BrainiacError, FileOperation};
 use gray_matter::ParsedEntityStruct;
-use model::{AnalyticsMetadata, ArticleGenre, InterestMetadata, Metadata, ResponseOutputType};
-use ollama_rs::Ollama;
-use slugify_rs::slugify;
+use model::{AnalyticsMetadata, Metadata, ResponseOutputType};
+use output::{Backup, CollisionPolicy, WriteStatus};
+use serde::Deserialize;
 
+mod chunk;
+mod client;
+pub mod config;
 pub mod error;
+mod history;
+pub mod keywords;
+pub mod lint;
+mod markdown;
 pub mod model;
 mod ollama;
+pub mod output;
+mod readability;
+mod stop_words;
+pub mod taxonomy;
+mod title;
 
-fn generate_article_matter(metadata: &Metadata) -> String {
+/// Default estimated token count above which the article is condensed before prompting.
+pub const DEFAULT_CHUNK_THRESHOLD: usize = 3000;
+
+fn generate_article_matter(metadata: &Metadata) -> Result<String, BrainiacError> {
     let matter = gray_matter::Matter::<gray_matter::engine::TOML>::new();
-    let t = toml::to_string_pretty(metadata).unwrap();
-    format!("{}\n{}\n{}\n", matter.delimiter, t, matter.delimiter)
+    let t = toml::to_string_pretty(metadata)?;
+    Ok(format!(
+        "{}\n{}\n{}\n",
+        matter.delimiter, t, matter.delimiter
+    ))
 }
-fn parse_article_matter(content: &str) -> Option<gray_matter::ParsedEntityStruct<Metadata>> {
+/// Parse the front matter of `content`, `path` is only used to describe errors.
+fn parse_article_matter(
+    content: &str,
+    path: Option<&Path>,
+) -> Result<Option<ParsedEntityStruct<Metadata>>, BrainiacError> {
     let matter = gray_matter::Matter::<gray_matter::engine::TOML>::new();
-    matter.parse_with_struct::<Metadata>(content)
+    let parsed = matter.parse(content);
+    if parsed.matter.trim().is_empty() {
+        return Ok(None);
+    }
+    let table = toml::from_str::<toml::Table>(&parsed.matter).map_err(|error| {
+        BrainiacError::FrontMatterParseError(
+            path.map(Path::to_path_buf),
+            error.message().to_string(),
+        )
+    })?;
+    let data = Metadata::deserialize(table).map_err(|error| {
+        BrainiacError::SchemaMismatch(path.map(Path::to_path_buf), error.message().to_string())
+    })?;
+    Ok(Some(ParsedEntityStruct {
+        data,
+        content: parsed.content,
+        excerpt: parsed.excerpt,
+        orig: parsed.orig,
+        matter: parsed.matter,
+    }))
 }
 fn sanitize_string(input: String) -> String {
     input.replace("\n", "").replace("\r", "").replace("\t", "")
 }
 
-struct AnalyticsData {
-    reading_time_in_minutes: u64,
-    length_in_words: u64,
-}
-fn get_analytics_data(content: &str) -> AnalyticsData {
-    let reading_options = estimated_read_time::Options::default();
-    let reading_time = estimated_read_time::text(content, &reading_options);
-    AnalyticsData {
-        reading_time_in_minutes: reading_time.seconds() / 60,
-        length_in_words: reading_time.word_count(),
+/// Words per minute used when estimating the time spent reading code blocks.
+const CODE_WORDS_PER_MINUTE: u32 = 100;
+/// Seconds spent on the first image, every following image takes one second less.
+const FIRST_IMAGE_SECONDS: u64 = 12;
+/// The minimum number of seconds spent on any image.
+const MIN_IMAGE_SECONDS: u64 = 3;
+
+fn get_analytics_data(content: &str, include_readability: bool) -> AnalyticsMetadata {
+    let stats = markdown::analyze(content);
+    let prose_time =
+        estimated_read_time::text(&stats.prose, &estimated_read_time::Options::default());
+    let code_time = estimated_read_time::text(
+        &stats.code,
+        &estimated_read_time::Options::new()
+            .wpm(CODE_WORDS_PER_MINUTE)
+            .build()
+            .unwrap_or_default(),
+    );
+    let image_seconds: u64 = (0..stats.image_count)
+        .map(|index| {
+            FIRST_IMAGE_SECONDS
+                .saturating_sub(index)
+                .max(MIN_IMAGE_SECONDS)
+        })
+        .sum();
+    let seconds = prose_time.seconds() + code_time.seconds() + image_seconds;
+
+    AnalyticsMetadata {
+        length_in_words: stats.prose.split_whitespace().count() as u64,
+        reading_time_in_minutes: seconds.div_ceil(60),
+        code_block_count: stats.code_block_count,
+        code_languages: stats.code_languages,
+        heading_depth: stats.heading_depth,
+        image_count: stats.image_count,
+        link_count: stats.link_count,
+        readability: include_readability
+            .then(|| readability::score(&stats.prose))
+            .flatten(),
+        content_hash: Some(markdown::content_hash(content)),
+        ..Default::default()
     }
 }
 
@@ -43,101 +115,133 @@ pub fn create_output_file_name(slug: &String) -> PathBuf {
     Path::new(&file_name).to_path_buf()
 }
 
-pub struct BrainiacAppend {
-    pub source_path: PathBuf,
-    pub output_dir_path: Option<PathBuf>,
+/// How long each step of the metadata generation took
+#[derive(Clone, Default)]
+pub struct Timings {
+    /// Time spent summarizing long content before generating fields
+    pub condense: Duration,
+    /// Time spent generating and formatting each field, in generation order
+    pub fields: Vec<(ResponseOutputType, Duration)>,
+    pub total: Duration,
+}
+
+/// Options for generating metadata from article content
+#[derive(Clone, Default)]
+pub struct BrainiacGenerate {
     pub gen_model: Option<String>,
     pub format_model: Option<String>,
     pub author: String,
+    /// Estimated token count above which the article is chunked and summarized before prompting
+    pub chunk_threshold: Option<usize>,
+    /// Include readability scores in the analytics metadata
+    pub readability: bool,
+}
+pub struct GeneratedDocument {
+    pub metadata: Metadata,
+    /// The rendered front matter that is prepended to the article
+    pub rendered_metadata: String,
+    /// The article with its existing front matter replaced by the rendered front matter
+    pub document: String,
+    /// The model used for generating metadata
+    pub gen_model: String,
+    /// The model used for formatting model responses
+    pub format_model: String,
+    pub timings: Timings,
+    /// The genre every generated genre label was matched to, with its confidence
+    pub genre_matches: Vec<taxonomy::GenreMatch>,
+    /// Generated genres that are not in the taxonomy and were left out of the metadata
+    pub unmatched_genres: Vec<String>,
 }
-/// Append metadata to a file.
-/// This function has the following side effects:
-/// - Reads the file at `source_path`
-/// - Generates metadata fields based on the model and path content
-/// - Writes the file to `output_path` or std out
-pub async fn append_metadata(params: BrainiacAppend) -> Result<Metadata, BrainiacError> {
-    let article_content = std::fs::read_to_string(params.source_path).unwrap();
-    let instance = Ollama::default();
-    let mut generator = ollama::generator::OutputGenerator::new(
-        &instance,
-        params.gen_model.unwrap_or("deepseek-r1:8b".to_string()),
-    );
-    generator.set_content(article_content.clone());
-    let formatter = ollama::formatter::OutputFormatter::new(
-        &instance,
-        params
-            .format_model
-            .unwrap_or("deepseek-r1:1.5b".to_string()),
-    );
 
-    let title = generator.generate_output(ResponseOutputType::Title).await?;
-    log::trace!("Unproccessed Title: {}\n", title.response);
-    let title = formatter
-        .format_output(sanitize_string(title.response), ResponseOutputType::Title)
-        .await?;
-    log::info!("Title: {}", title.response);
-    let description = generator
-        .generate_output(ResponseOutputType::Description)
-        .await?;
-    log::trace!("Unproccessed Description: {}\n", description.response);
-    let description = formatter
-        .format_output(
-            sanitize_string(description.response),
-            ResponseOutputType::Description,
-        )
-        .await?;
-    log::info!("Description: {}", description.response);
-    let genre = generator.generate_output(ResponseOutputType::Genre).await?;
-    log::trace!("Unproccessed Genre: {}", genre.response);
-    let genre = formatter
-        .format_output(sanitize_string(genre.response), ResponseOutputType::Genre)
-        .await?;
-    log::info!("Genre: {}", genre.response);
-    let keywords = generator
-        .generate_output(ResponseOutputType::Keywords)
-        .await?;
-    log::trace!("Unproccessed Keywords: {}", keywords.response);
-    let keywords = formatter
-        .format_output(
-            sanitize_string(keywords.response),
-            ResponseOutputType::Keywords,
-        )
-        .await?;
-    log::info!("Keywords: {}", keywords.response);
-
-    let analytics = get_analytics_data(&article_content);
-    let metadata = Metadata {
-        title: title.response.clone(),
-        description: description.response,
-        author: params.author,
-        slug: slugify!(title.response.as_str()),
-        analytics: AnalyticsMetadata {
-            reading_time_in_minutes: analytics.reading_time_in_minutes,
-            length_in_words: analytics.length_in_words,
-            ..Default::default()
-        },
-        interest: InterestMetadata {
-            keywords: vec![keywords.response],
-            genre: ArticleGenre::from_str(genre.response.as_str()).unwrap_or_default(),
-            ..Default::default()
-        },
-    };
-    let rendered_metadata = generate_article_matter(&metadata);
-    println!("{}", rendered_metadata);
-    if params.output_dir_path.is_some() {
-        let output_dir_path = params.output_dir_path.unwrap();
-        let output_path = Path::new(&output_dir_path).join(create_output_file_name(&metadata.slug));
-        let mut file = std::fs::File::create_new(output_path).unwrap();
-        let buffered_content = format!("{}\n{}", rendered_metadata, article_content);
-        let _ = file.write(buffered_content.as_bytes())?;
-        Ok(metadata)
-    } else {
-        let file_name = create_output_file_name(&metadata.slug);
-        let mut file = std::fs::File::create_new(Path::new(&file_name)).unwrap();
-        let buffered_content = format!("{}\n\n{}", rendered_metadata, article_content);
-        let _ = file.write(buffered_content.as_bytes())?;
-        Ok(metadata)
+/// Generate metadata for article content held in memory.
+/// This function has no file system side effects, the dates come from existing front matter or
+/// default to today.
+pub async fn generate_metadata(
+    content: &str,
+    options: &BrainiacGenerate,
+) -> Result<GeneratedDocument, BrainiacError> {
+    BrainiacBuilder::from(options.clone())
+        .build()?
+        .generate_metadata(content)
+        .await
+}
+
+/// Where the article is read from
+#[derive(Clone)]
+pub enum InputSource {
+    File(PathBuf),
+    Stdin,
+}
+
+impl InputSource {
+    /// The path of the article, `None` when it is read from stdin.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            InputSource::File(path) => Some(path),
+            InputSource::Stdin => None,
+        }
     }
+
+    fn read(&self) -> Result<String, BrainiacError> {
+        match self {
+            InputSource::File(path) => std::fs::read_to_string(path)
+                .map_err(BrainiacError::file(FileOperation::Read, path)),
+            InputSource::Stdin => {
+                let mut content = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut content)
+                    .map_err(BrainiacError::file(FileOperation::Read, Path::new("-")))?;
+                Ok(content)
+            }
+        }
+    }
+}
+
+/// Where the article with its front matter is written
+#[derive(Clone)]
+pub enum OutputTarget {
+    /// A new `<slug>.md` file in the directory, an empty path is the current directory
+    Directory(PathBuf),
+    /// Replace the front matter of the source file
+    InPlace,
+    /// Nothing is written, the rendered document is returned for the caller to stream
+    Stdout,
+}
+
+pub struct BrainiacAppend {
+    pub source: InputSource,
+    pub output: OutputTarget,
+    pub generate: BrainiacGenerate,
+    /// What to do when the output file already exists
+    pub on_collision: CollisionPolicy,
+    /// Resolve the output path without writing the file
+    pub dry_run: bool,
+    /// Keep a copy of any file that is replaced
+    pub backup: Option<Backup>,
+}
+pub struct BrainiacAppendOutput {
+    pub generated: GeneratedDocument,
+    /// The written file, `None` when the output target is stdout
+    pub output_path: Option<PathBuf>,
+    pub status: WriteStatus,
+}
+/// Append metadata to an article.
+/// This function has the following side effects:
+/// - Reads the article from the `source` file or stdin
+/// - Generates metadata fields based on the model and article content
+/// - Writes the article to the `output` target, unless `dry_run` is set
+/// - Copies a replaced file to its `backup`
+pub async fn append_metadata(
+    params: BrainiacAppend,
+) -> Result<BrainiacAppendOutput, BrainiacError> {
+    let mut builder = BrainiacBuilder::from(params.generate).on_collision(params.on_collision);
+    if let Some(backup) = params.backup {
+        builder = builder.backup(backup);
+    }
+    builder
+        .build()?
+        .append(&params.source, &params.output, params.dry_run)
+        .await
 }
 
 pub struct BrainiacParse {
@@ -148,8 +252,44 @@ pub struct BrainiacParse {
 /// - Reads the file at `source_path`
 /// - Extracts metadata fields from the file
 /// - Extracts content from the file
-/// - Returns the metadata and content
-pub fn parse_metadata(params: BrainiacParse) -> Option<ParsedEntityStruct<model::Metadata>> {
-    let content = std::fs::read_to_string(params.source_path).unwrap();
-    parse_article_matter(content.as_str())
+/// - Returns the metadata and content, or `None` when the file has no brainiac front matter
+pub fn parse_metadata(
+    params: BrainiacParse,
+) -> Result<Option<ParsedEntityStruct<model::Metadata>>, BrainiacError> {
+    let source_path = Path::new(&params.source_path);
+    let content = std::fs::read_to_string(source_path)
+        .map_err(BrainiacError::file(FileOperation::Read, source_path))?;
+    parse_article_matter(content.as_str(), Some(source_path))
+}
+
+/// Parse metadata from article content held in memory.
+/// Returns the metadata and content, or `None` when the content has no brainiac front matter.
+pub fn parse_content(
+    content: &str,
+) -> Result<Option<ParsedEntityStruct<model::Metadata>>, BrainiacError> {
+    parse_article_matter(content, None)
+}
+
+/// Whether the front matter of `content` was generated from its current body.
+/// Articles without front matter, or with front matter that has no content hash, are not current.
+pub fn has_current_metadata(content: &str) -> bool {
+    match parse_article_matter(content, None) {
+        Ok(Some(parsed)) => {
+            parsed.data.analytics.content_hash.as_deref()
+                == Some(markdown::content_hash(content).as_str())
+        }
+        _ => false,
+    }
+}
+
+pub struct BrainiacRestore {
+    pub source_path: PathBuf,
+    pub backup: Backup,
+}
+/// Restore a file from its backup.
+/// This function has the following side effects:
+/// - Replaces the file at `source_path` with its backup
+/// - Removes the backup
+pub fn restore_article(params: BrainiacRestore) -> Result<PathBuf, BrainiacError> {
+    output::restore_backup(&params.source_path, &params.backup)
 }
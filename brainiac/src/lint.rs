@@ -0,0 +1,255 @@
+use serde::Serialize;
+use slugify_rs::slugify;
+
+use crate::error::BrainiacError;
+use crate::markdown;
+use crate::model::ResponseOutputType;
+use crate::readability;
+use crate::taxonomy::Taxonomy;
+use crate::title::MAX_TITLE_WORDS;
+
+/// The maximum number of sentences in a description, matching the generation prompt.
+const MAX_DESCRIPTION_SENTENCES: usize = 5;
+/// The relative difference in word count above which the analytics are considered stale.
+const STALE_WORD_COUNT_RATIO: f64 = 0.1;
+
+/// A check made against the front matter of an article
+#[derive(Debug, Clone, Copy, PartialEq, strum::Display, Serialize)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    MissingFrontMatter,
+    InvalidFrontMatter,
+    EmptyTitle,
+    LongTitle,
+    EmptyDescription,
+    LongDescription,
+    /// The slug does not match the title
+    SlugMismatch,
+    MissingKeywords,
+    MissingGenre,
+    /// A genre that is not in the taxonomy
+    UnknownGenre,
+    /// The word count no longer matches the article body
+    StaleAnalytics,
+    /// The table of contents no longer matches the article headings
+    StaleToc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::Display, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    pub rule: LintRule,
+    pub severity: Severity,
+    /// The generated field the issue is about, regenerating it resolves the issue
+    pub field: Option<ResponseOutputType>,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(
+        rule: LintRule,
+        severity: Severity,
+        field: Option<ResponseOutputType>,
+        message: String,
+    ) -> Self {
+        Self {
+            rule,
+            severity,
+            field,
+            message,
+        }
+    }
+}
+
+/// Check the front matter of `content` against the article body and the genre `taxonomy`.
+/// Front matter that can not be parsed is reported as an issue instead of an error.
+pub fn lint(content: &str, taxonomy: &Taxonomy) -> Result<Vec<LintIssue>, BrainiacError> {
+    let parsed = match crate::parse_article_matter(content, None) {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => {
+            return Ok(vec![LintIssue::new(
+                LintRule::MissingFrontMatter,
+                Severity::Error,
+                None,
+                "The article has no front matter".to_string(),
+            )])
+        }
+        Err(
+            BrainiacError::FrontMatterParseError(_, message)
+            | BrainiacError::SchemaMismatch(_, message),
+        ) => {
+            return Ok(vec![LintIssue::new(
+                LintRule::InvalidFrontMatter,
+                Severity::Error,
+                None,
+                message,
+            )])
+        }
+        Err(error) => return Err(error),
+    };
+    let metadata = parsed.data;
+    let mut issues = vec![];
+
+    let title_words = metadata.title.split_whitespace().count();
+    if title_words == 0 {
+        issues.push(LintIssue::new(
+            LintRule::EmptyTitle,
+            Severity::Error,
+            Some(ResponseOutputType::Title),
+            "The title is empty".to_string(),
+        ));
+    } else if title_words > MAX_TITLE_WORDS {
+        issues.push(LintIssue::new(
+            LintRule::LongTitle,
+            Severity::Warning,
+            Some(ResponseOutputType::Title),
+            format!(
+                "The title has {} words, at most {} are expected",
+                title_words, MAX_TITLE_WORDS
+            ),
+        ));
+    }
+    if !metadata.title.trim().is_empty() && slugify!(metadata.title.as_str()) != metadata.slug {
+        issues.push(LintIssue::new(
+            LintRule::SlugMismatch,
+            Severity::Warning,
+            Some(ResponseOutputType::Title),
+            format!("The slug {} does not match the title", metadata.slug),
+        ));
+    }
+
+    let sentences = readability::sentence_count(&metadata.description);
+    if metadata.description.trim().is_empty() {
+        issues.push(LintIssue::new(
+            LintRule::EmptyDescription,
+            Severity::Error,
+            Some(ResponseOutputType::Description),
+            "The description is empty".to_string(),
+        ));
+    } else if sentences > MAX_DESCRIPTION_SENTENCES {
+        issues.push(LintIssue::new(
+            LintRule::LongDescription,
+            Severity::Warning,
+            Some(ResponseOutputType::Description),
+            format!(
+                "The description has {} sentences, at most {} are expected",
+                sentences, MAX_DESCRIPTION_SENTENCES
+            ),
+        ));
+    }
+
+    if metadata.interest.keywords.is_empty() {
+        issues.push(LintIssue::new(
+            LintRule::MissingKeywords,
+            Severity::Warning,
+            Some(ResponseOutputType::Keywords),
+            "The article has no keywords".to_string(),
+        ));
+    }
+
+    if metadata.interest.genres.is_empty() {
+        issues.push(LintIssue::new(
+            LintRule::MissingGenre,
+            Severity::Warning,
+            Some(ResponseOutputType::Genre),
+            "The article has no genre".to_string(),
+        ));
+    }
+    // Stored genres must be exact taxonomy paths, close matches only serve as a suggestion
+    for genre in &metadata.interest.genres {
+        let message = match taxonomy.resolve(genre) {
+            Some(matched) if matched.confidence == 1.0 && matched.genre == *genre => continue,
+            Some(matched) => format!(
+                "The genre {} is not in the taxonomy, did you mean {}?",
+                genre, matched.genre
+            ),
+            None => format!("The genre {} is not in the taxonomy", genre),
+        };
+        issues.push(LintIssue::new(
+            LintRule::UnknownGenre,
+            Severity::Warning,
+            Some(ResponseOutputType::Genre),
+            message,
+        ));
+    }
+
+    let stats = markdown::analyze(&parsed.content);
+    let words = stats.prose.split_whitespace().count() as f64;
+    let recorded = metadata.analytics.length_in_words as f64;
+    if (words - recorded).abs() > words.max(recorded) * STALE_WORD_COUNT_RATIO {
+        issues.push(LintIssue::new(
+            LintRule::StaleAnalytics,
+            Severity::Warning,
+            None,
+            format!(
+                "The analytics record {} words but the article has {}",
+                recorded, words
+            ),
+        ));
+    }
+
+    let toc = markdown::extract_toc(&parsed.content);
+    let toc_matches = toc.len() == metadata.toc.len()
+        && toc
+            .iter()
+            .zip(metadata.toc.iter())
+            .all(|(current, recorded)| current.anchor == recorded.anchor);
+    if !toc_matches {
+        issues.push(LintIssue::new(
+            LintRule::StaleToc,
+            Severity::Warning,
+            None,
+            "The table of contents does not match the article headings".to_string(),
+        ));
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genre_issues(genres: &str) -> Vec<LintIssue> {
+        let content = format!(
+            "---\ntitle = \"Bananas\"\ndescription = \"About bananas.\"\nauthor = \"Me\"\nslug = \"bananas\"\n\n[analytics]\ncreated_at = \"2025-01-01\"\nlength_in_words = 2\nreading_time_in_minutes = 1\n\n[interest]\nkeywords = [\"banana\"]\ngenres = {}\nrelated_articles = []\n---\nBananas rock.\n",
+            genres
+        );
+        lint(&content, &Taxonomy::default())
+            .unwrap()
+            .into_iter()
+            .filter(|issue| issue.rule == LintRule::UnknownGenre)
+            .collect()
+    }
+
+    #[test]
+    fn exact_genre_paths_pass() {
+        assert!(genre_issues("[\"FOOD\", \"HEALTH\"]").is_empty());
+    }
+
+    #[test]
+    fn close_genres_are_flagged_with_a_suggestion() {
+        for genre in ["Technolgy", "Tech", "technology"] {
+            let issues = genre_issues(&format!("[\"{}\"]", genre));
+            assert_eq!(issues.len(), 1, "{}", genre);
+            assert!(issues[0].message.ends_with("did you mean TECHNOLOGY?"));
+        }
+    }
+
+    #[test]
+    fn unknown_genres_are_flagged() {
+        let issues = genre_issues("[\"Quantum Gardening\"]");
+        assert_eq!(
+            issues[0].message,
+            "The genre Quantum Gardening is not in the taxonomy"
+        );
+    }
+}
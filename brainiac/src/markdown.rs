@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use slugify_rs::slugify;
+
+use crate::model::TocEntry;
+
+/// The anchor of a heading without any sluggable text.
+const EMPTY_HEADING_ANCHOR: &str = "section";
+
+fn parser_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
+}
+
+fn code_block_language(kind: &CodeBlockKind) -> String {
+    match kind {
+        CodeBlockKind::Fenced(info) => info
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase(),
+        CodeBlockKind::Indented => String::new(),
+    }
+}
+
+/// Remove any existing front matter from the article and return the body.
+pub fn strip_front_matter(content: &str) -> String {
+    let matter = gray_matter::Matter::<gray_matter::engine::TOML>::new();
+    matter.parse(content).content
+}
+
+/// A SHA-256 hex digest of the article body, ignoring front matter and surrounding whitespace.
+pub fn content_hash(content: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(strip_front_matter(content).trim().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Convert a markdown article into the prose that is sent to the model.
+/// - Existing front matter, html, footnotes and link urls are dropped
+/// - Code blocks are replaced by a short placeholder naming the language and size
+/// - Images are replaced by their alt text
+/// - Headings and list items keep a markdown prefix as structure hints
+pub fn to_prompt_text(content: &str) -> String {
+    let body = strip_front_matter(content);
+    let mut text = String::new();
+    // Depth of elements whose content is dropped entirely
+    let mut skip_depth = 0;
+    let mut code_block: Option<(String, usize)> = None;
+    let mut list_depth = 0;
+
+    for event in Parser::new_ext(&body, parser_options()) {
+        if let Some((_, lines)) = code_block.as_mut() {
+            match event {
+                Event::Text(code) => *lines += code.lines().count(),
+                Event::End(TagEnd::CodeBlock) => {
+                    let (language, lines) = code_block.take().unwrap_or_default();
+                    let language = if language.is_empty() {
+                        "code".to_string()
+                    } else {
+                        language
+                    };
+                    text.push_str(&format!("[{} block, {} lines]\n\n", language, lines));
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) | Event::Start(Tag::FootnoteDefinition(_)) => {
+                skip_depth = 1
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                code_block = Some((code_block_language(&kind), 0));
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                text.push_str(&format!("{} ", "#".repeat(level as usize)));
+            }
+            Event::Start(Tag::List(_)) => {
+                if list_depth > 0 && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                list_depth += 1;
+            }
+            Event::End(TagEnd::List(_)) => {
+                list_depth -= 1;
+                if list_depth == 0 {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&format!("{}- ", "  ".repeat(list_depth - 1)));
+            }
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::Start(Tag::Image { .. }) => text.push_str("[Image: "),
+            Event::End(TagEnd::Image) => text.push(']'),
+            Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::BlockQuote(_))
+            | Event::End(TagEnd::Table)
+                if list_depth == 0 =>
+            {
+                text.push_str("\n\n")
+            }
+            Event::End(TagEnd::TableRow) | Event::End(TagEnd::TableHead) => text.push('\n'),
+            Event::End(TagEnd::TableCell) => text.push_str(" | "),
+            Event::Text(value) | Event::Code(value) => text.push_str(&value),
+            Event::InlineMath(value) | Event::DisplayMath(value) => text.push_str(&value),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+
+    text.trim().to_string()
+}
+
+/// Structural statistics of a markdown article.
+#[derive(Default)]
+pub struct MarkdownStats {
+    /// The readable text of the article, excluding code, html and front matter
+    pub prose: String,
+    /// The contents of every code block joined together
+    pub code: String,
+    pub code_block_count: u64,
+    /// Distinct code block languages in order of appearance
+    pub code_languages: Vec<String>,
+    /// The deepest heading level used, 0 when there are no headings
+    pub heading_depth: u8,
+    pub image_count: u64,
+    pub link_count: u64,
+}
+
+/// Walk the markdown AST of the article body and collect its structural statistics.
+pub fn analyze(content: &str) -> MarkdownStats {
+    let body = strip_front_matter(content);
+    let mut stats = MarkdownStats::default();
+    let mut skip_depth = 0;
+    let mut in_code_block = false;
+
+    for event in Parser::new_ext(&body, parser_options()) {
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => skip_depth = 1,
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                stats.code_block_count += 1;
+                let language = code_block_language(&kind);
+                if !language.is_empty() && !stats.code_languages.contains(&language) {
+                    stats.code_languages.push(language);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                stats.code.push('\n');
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                stats.heading_depth = stats.heading_depth.max(level as u8);
+            }
+            Event::Start(Tag::Image { .. }) => stats.image_count += 1,
+            Event::Start(Tag::Link { .. }) => stats.link_count += 1,
+            Event::Text(value) if in_code_block => stats.code.push_str(&value),
+            Event::Text(value) | Event::Code(value) => stats.prose.push_str(&value),
+            Event::SoftBreak | Event::HardBreak => stats.prose.push(' '),
+            Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Item)
+            | Event::End(TagEnd::TableCell) => stats.prose.push('\n'),
+            _ => {}
+        }
+    }
+
+    stats
+}
+
+/// Build the table of contents from the heading hierarchy of the article.
+/// Anchors use the same slug format as `Metadata.slug`, repeated anchors get the next free numeric suffix.
+pub fn extract_toc(content: &str) -> Vec<TocEntry> {
+    let body = strip_front_matter(content);
+    let mut toc = vec![];
+    let mut anchors: HashSet<String> = HashSet::new();
+    let mut heading: Option<(u8, String)> = None;
+
+    for event in Parser::new_ext(&body, parser_options()) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((level as u8, String::new()))
+            }
+            Event::Text(value) | Event::Code(value) => {
+                if let Some((_, text)) = heading.as_mut() {
+                    text.push_str(&value);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((level, text)) = heading.take() else {
+                    continue;
+                };
+                let text = text.trim().to_string();
+                let mut slug = slugify!(text.as_str());
+                if slug.is_empty() {
+                    slug = EMPTY_HEADING_ANCHOR.to_string();
+                }
+                let mut anchor = slug.clone();
+                let mut suffix = 1;
+                while !anchors.insert(anchor.clone()) {
+                    anchor = format!("{}-{}", slug, suffix);
+                    suffix += 1;
+                }
+                toc.push(TocEntry {
+                    level,
+                    text,
+                    anchor,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    toc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchors(content: &str) -> Vec<String> {
+        extract_toc(content)
+            .into_iter()
+            .map(|entry| entry.anchor)
+            .collect()
+    }
+
+    #[test]
+    fn toc_follows_the_heading_hierarchy() {
+        let toc = extract_toc("# Getting `started`\n\ntext\n\n## Install it\n");
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].level, 1);
+        assert_eq!(toc[0].text, "Getting started");
+        assert_eq!(toc[0].anchor, "getting-started");
+        assert_eq!(toc[1].level, 2);
+        assert_eq!(toc[1].anchor, "install-it");
+    }
+
+    #[test]
+    fn repeated_headings_get_unique_anchors() {
+        assert_eq!(
+            anchors("# Intro\n\n# Intro\n\n# Intro 1\n\n# Intro\n"),
+            ["intro", "intro-1", "intro-1-1", "intro-2"]
+        );
+        assert_eq!(
+            anchors("# Intro 1\n\n# Intro\n\n# Intro\n"),
+            ["intro-1", "intro", "intro-2"]
+        );
+    }
+
+    #[test]
+    fn empty_headings_get_an_anchor() {
+        assert_eq!(anchors("#\n\n# !!!\n"), ["section", "section-1"]);
+    }
+}
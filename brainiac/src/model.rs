@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(
     Default,
@@ -36,43 +36,109 @@ pub struct Metadata {
     pub description: String,
     pub author: String,
     pub slug: String,
+    /// Other generated titles, best first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternate_titles: Vec<String>,
     pub analytics: AnalyticsMetadata,
     pub interest: InterestMetadata,
+    #[serde(default)]
+    pub toc: Vec<TocEntry>,
+}
+
+/// A heading of the article used to render a table of contents.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    /// The heading slug, unique within the article
+    pub anchor: String,
+}
+
+fn today() -> chrono::NaiveDate {
+    chrono::Utc::now().date_naive()
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AnalyticsMetadata {
     pub created_at: chrono::NaiveDate,
+    #[serde(default = "today")]
+    pub updated_at: chrono::NaiveDate,
+    /// Number of words of prose, excluding code, markup and front matter
     pub length_in_words: u64,
     pub reading_time_in_minutes: u64,
+    #[serde(default)]
+    pub code_block_count: u64,
+    #[serde(default)]
+    pub code_languages: Vec<String>,
+    /// The deepest heading level used in the article
+    #[serde(default)]
+    pub heading_depth: u8,
+    #[serde(default)]
+    pub image_count: u64,
+    #[serde(default)]
+    pub link_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readability: Option<ReadabilityMetadata>,
+    /// A digest of the article body the metadata was generated from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
 }
 impl Default for AnalyticsMetadata {
     fn default() -> Self {
         Self {
-            created_at: chrono::Utc::now().date_naive(),
+            created_at: today(),
+            updated_at: today(),
             length_in_words: 0,
             reading_time_in_minutes: 5,
+            code_block_count: 0,
+            code_languages: vec![],
+            heading_depth: 0,
+            image_count: 0,
+            link_count: 0,
+            readability: None,
+            content_hash: None,
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
+pub struct ReadabilityMetadata {
+    pub flesch_reading_ease: f64,
+    pub flesch_kincaid_grade: f64,
+    /// Average number of words per sentence
+    pub average_sentence_length: f64,
+    /// Ratio of unique words to total words
+    pub lexical_diversity: f64,
+}
+
+/// Accept a single string as well as a list, front matter written before articles could
+/// have several genres has a single `genre` string.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct InterestMetadata {
     pub keywords: Vec<String>,
-    pub genre: ArticleGenre,
+    /// Genre paths from the taxonomy, such as `Technology > Programming`, most relevant first
+    #[serde(default, alias = "genre", deserialize_with = "one_or_many")]
+    pub genres: Vec<String>,
     pub related_articles: Vec<String>,
 }
-impl Default for InterestMetadata {
-    fn default() -> Self {
-        Self {
-            keywords: vec![],
-            genre: ArticleGenre::Opinion,
-            related_articles: vec![],
-        }
-    }
-}
 
 /// The type of output to generate by llm
+#[derive(Debug, Clone, Copy, PartialEq, strum::Display, strum::EnumString, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum ResponseOutputType {
     Title,
     Description,
@@ -3,52 +3,89 @@ use ollama_rs::{
     Ollama,
 };
 
-use super::response::JSONResponse;
-use crate::model::{ArticleGenre, ResponseOutputType};
+use super::response::{self, JSONResponse};
+use crate::error::{BrainiacError, ModelContext};
+use crate::keywords::KeywordSettings;
+use crate::model::ResponseOutputType;
+use crate::taxonomy::GENRE_SEPARATOR;
 
 pub struct OutputFormatter<'a> {
     instance: &'a Ollama,
     model: String,
+    genres: Vec<String>,
+}
+
+/// Example genre responses built from the available `genres`, one of them with a nested genre
+/// when the taxonomy has one.
+fn genre_examples(genres: &[String]) -> [Vec<String>; 3] {
+    let genre = |index: usize| genres.iter().cycle().nth(index).cloned();
+    let nested = genres
+        .iter()
+        .find(|genre| genre.contains(GENRE_SEPARATOR))
+        .cloned();
+    let example = |values: Vec<Option<String>>| {
+        let mut example = values.into_iter().flatten().collect::<Vec<String>>();
+        example.dedup();
+        example
+    };
+    [
+        example(vec![genre(0)]),
+        example(vec![genre(1), genre(2)]),
+        example(vec![nested.or(genre(3)), genre(4)]),
+    ]
 }
 
 impl<'a> OutputFormatter<'a> {
     pub fn new(instance: &'a Ollama, model: String) -> Self {
-        Self { instance, model }
+        Self {
+            instance,
+            model,
+            genres: vec![],
+        }
+    }
+
+    /// Genres: The genre paths the example responses are built from
+    pub fn set_genres(&mut self, genres: Vec<String>) {
+        self.genres = genres;
     }
 
     pub async fn format_output(
         &self,
         content: String,
         output_type: ResponseOutputType,
-    ) -> ollama_rs::error::Result<GenerationResponse> {
+    ) -> Result<GenerationResponse, BrainiacError> {
         let system = "You are a REST API and can only respond in JSON. You have been given a strict structure to respond in.";
         let example_responses = match output_type {
             ResponseOutputType::Description => [
-                JSONResponse::from(" The Middle Way in Buddhism is a philosophy that emphasizes balance, harmony, and respect for the intrinsic dignity of all life. Rooted in the teachings of Shakyamuni Buddha, it advocates against extreme views, whether they be luxurious indulgence or harsh asceticism, promoting instead a moderate path that fosters inner peace and wisdom. Zhiyi further developed this concept by introducing three truths: the truth of temporary existence (physical aspects), the truth of non-substantiality (mental and spiritual aspects), and the essence of life that unifies these opposites. This holistic view underscores the interconnectedness of body, mind, and environment. Nichiren expanded on this by describing life as an elusive reality, neither merely existing nor non-existing but embodying both qualities. He stressed the inherent dignity in all life, encouraging a compassionate and harmonious approach to existence. This philosophy aligns with Gandhi's assertion about considering the poorest man, linking the Middle Way to social justice. The Soka Gakkai envisions the Middle Way as a path that transforms individuals and societies toward happiness and coexistence. It involves living positively, constantly reflecting on actions to ensure they align with humanity's ethical path. This principle is seen as a guiding light against the extremes of modernity, offering a pathway towards peace and fulfillment. In essence, the Middle Way is not just about personal enlightenment but also about fostering social harmony and collective well-being, respecting life's sanctity, and seeking wisdom to benefit both individuals and society.") .to_string(),
+                JSONResponse::from(" The Middle Way in Buddhism is a philosophy that emphasizes balance, harmony, and respect for the intrinsic dignity of all life. Rooted in the teachings of Shakyamuni Buddha, it advocates against extreme views, whether they be luxurious indulgence or harsh asceticism, promoting instead a moderate path that fosters inner peace and wisdom. Zhiyi further developed this concept by introducing three truths: the truth of temporary existence (physical aspects), the truth of non-substantiality (mental and spiritual aspects), and the essence of life that unifies these opposites. This holistic view underscores the interconnectedness of body, mind, and environment. Nichiren expanded on this by describing life as an elusive reality, neither merely existing nor non-existing but embodying both qualities. He stressed the inherent dignity in all life, encouraging a compassionate and harmonious approach to existence. This philosophy aligns with Gandhi's assertion about considering the poorest man, linking the Middle Way to social justice. The Soka Gakkai envisions the Middle Way as a path that transforms individuals and societies toward happiness and coexistence. It involves living positively, constantly reflecting on actions to ensure they align with humanity's ethical path. This principle is seen as a guiding light against the extremes of modernity, offering a pathway towards peace and fulfillment. In essence, the Middle Way is not just about personal enlightenment but also about fostering social harmony and collective well-being, respecting life's sanctity, and seeking wisdom to benefit both individuals and society.") .to_json()?,
                 JSONResponse::from( " The article explores prevalent competitive Pokémon strategies, highlighting their effectiveness and complexity. Despite the vast array of Pokémon and moves, certain strategies consistently dominate, offering both offensive and defensive advantages. 1. **Perish Traps**: Utilize the Perish Song move to KO opponents after three turns. Enhanced with abilities like Shadow Tag, these teams trap foes, making it easier to apply the finishing move. 2. **Hazard Stacks**: Employ multiple Hazard moves such as Stealth Rock and Spikes to progressively chip away at opponents' health. Teams often use dedicated setters and a mix of defensive and offensive Pokémon to maintain balance. 3. **Sun Teams**: Leverage harsh sunlight to boost fire-type attacks and abilities like Chlorophyll. These teams are known for their offensive prowess, especially with Dought-boosted Pokémon. 4. **Rain Teams**: Utilize the Rain Weather Effect to enhance Swift Swim moves and reduce fire damage. This strategy allows for swift sweeps and defensive positioning with rain-resistant Pokémon. 5. **Sandstorm Teams**: Initiate sandstorms with Tyranitar's Sandstream ability, causing widespread damage unless countered by specific types like rock or ground. These strategies illustrate how strategic team compositions and weather conditions can tilt the balance in competitive Pokémon battles, making them both challenging and thrilling for players. ")
-                .to_string(),
+                .to_json()?,
                 JSONResponse::from( " Nietzsche's exploration of nihilism serves as a cornerstone in understanding the intellectual landscape of the late 19th and early 20th centuries. Nihilism, as defined by Nietzsche, posits the absence of inherent meaning or value in life, challenging conventional moral and religious frameworks. He viewed this stance with apprehension, recognizing its potential to undermine societal values and belief systems. In the 20th century, nihilistic themes became prominent in philosophy and literature, particularly through the works of existentialists like Sartre and Camus, who grappled with the 'absurd' nature of existence. While these thinkers often expressed despair, they also sought ways to live authentically despite the lack of inherent purpose. This era witnessed a blend of gloom and resilience as individuals navigated the complexities of meaninglessness. Postmodernity shares a skepticism towards grand narratives and meta-discourses, aligning with nihilistic ideas about the baselessness of values. However, postmodernity doesn't equate to nihilism itself; it's more about the critical engagement with these stories rather than their outright rejection. Nietzsche believed that if we deconstruct existing interpretations, we might uncover new paths forward—a process akin to postmodern deconstruction leading to reconstruction. Language plays a crucial role in this discourse, as Nietzsche argued that language and metaphors shape our understanding of reality. If language fails us, so does our ability to communicate meaning, underscoring the epistemological challenges inherent in nihilism. Social critics and artists have often depicted themes of alienation and existential despair, reflecting nihilistic ideas about the absence of inherent purpose. While Nietzsche saw nihilism as a necessary destruction leading to potential renewal, others may view it as an insurmountable problem without transcendence or belief. Practically, nihilism raises questions about moral relativism and how individuals navigate their lives without inherent meaning. Some counter this by finding localized meaning in relationships, art, and personal goals, suggesting that meaning can be constructed on a smaller scale. Antifoundationalism, the notion that there's no foundation for knowledge or value, ties into postmodern views of truth as constructed rather than discovered. This perspective resonates with nihilistic themes about the baselessness of values. In conclusion, Nietzsche's ideas on nihilism provide a rich tapestry to explore its evolution and impact across centuries. From its philosophical origins to its influence in shaping artistic and cultural responses, nihilism continues to be a significant lens through which we view the human condition. It challenges us to consider the possibilities of meaning-making in an era marked by doubt and inquiry. ")
-                .to_string(),
+                .to_json()?,
             ],
             ResponseOutputType::Title => [
-                JSONResponse::from("The Fall of the Roman Empire").to_string(),
-                JSONResponse::from("The Economic Impact of Climate Change").to_string(),
-                JSONResponse::from("The Relationship Between Art and Politics").to_string(),
-            ],
-            ResponseOutputType::Genre => [
-                JSONResponse::from(ArticleGenre::Art.to_string()).to_string(),
-                JSONResponse::from(ArticleGenre::Opinion.to_string()).to_string(),
-                JSONResponse::from(ArticleGenre::Technology.to_string()).to_string(),
+                JSONResponse::from("The Fall of the Roman Empire").to_json()?,
+                JSONResponse::from("The Economic Impact of Climate Change").to_json()?,
+                JSONResponse::from("The Relationship Between Art and Politics").to_json()?,
             ],
+            ResponseOutputType::Genre => {
+                let [first, second, third] = genre_examples(&self.genres);
+                [
+                    JSONResponse::from(first).to_json()?,
+                    JSONResponse::from(second).to_json()?,
+                    JSONResponse::from(third).to_json()?,
+                ]
+            }
             ResponseOutputType::Keywords => [
                 JSONResponse::from(vec!["Roman Empire".to_string(), "History".to_string()])
-                    .to_string(),
+                    .to_json()?,
                 JSONResponse::from(vec!["Climate Change".to_string(), "Economy".to_string()])
-                    .to_string(),
-                JSONResponse::from(vec!["Art".to_string(), "Politics".to_string()]).to_string(),
+                    .to_json()?,
+                JSONResponse::from(vec!["Art".to_string(), "Politics".to_string()]).to_json()?,
             ],
         };
 
+        let context = self.context(output_type, &content);
         let request = GenerationRequest::new(
             self.model.clone(),
             format!(
@@ -60,7 +97,63 @@ impl<'a> OutputFormatter<'a> {
         .system(system.to_string())
         .format(ollama_rs::generation::parameters::FormatType::Json);
 
-        self.instance.generate(request).await
+        self.instance
+            .generate(request)
+            .await
+            .map_err(|error| BrainiacError::OllamaDetailedError(error, context))
+    }
+
+    /// Format `content` and validate that the model responded with a single value.
+    pub async fn format_text(
+        &self,
+        content: String,
+        output_type: ResponseOutputType,
+    ) -> Result<String, BrainiacError> {
+        let context = self.context(output_type, &content);
+        let output = self.format_output(content, output_type).await?;
+        response::parse_text(&output.response)
+            .map_err(|message| BrainiacError::ValidationError(context, message))
+    }
+
+    /// Format `content` and validate that the model responded with a list of values.
+    pub async fn format_list(
+        &self,
+        content: String,
+        output_type: ResponseOutputType,
+    ) -> Result<Vec<String>, BrainiacError> {
+        let context = self.context(output_type, &content);
+        let output = self.format_output(content, output_type).await?;
+        response::parse_list(&output.response)
+            .map_err(|message| BrainiacError::ValidationError(context, message))
+    }
+
+    /// Format `content` as keywords and clean them up with `settings`.
+    /// Fails validation when no keyword is left.
+    pub async fn format_keywords(
+        &self,
+        content: String,
+        settings: &KeywordSettings,
+    ) -> Result<Vec<String>, BrainiacError> {
+        let field = ResponseOutputType::Keywords;
+        let context = self.context(field, &content);
+        let output = self.format_output(content, field).await?;
+        let keywords = settings.normalize(vec![output.response]);
+        if keywords.is_empty() {
+            return Err(BrainiacError::ValidationError(
+                context,
+                "no keywords are left after normalizing the response".to_string(),
+            ));
+        }
+        Ok(keywords)
+    }
+
+    fn context(&self, field: ResponseOutputType, content: &str) -> ModelContext {
+        ModelContext {
+            model: self.model.clone(),
+            field: Some(field),
+            prompt: Some(content.to_string()),
+            path: None,
+        }
     }
 }
 
@@ -71,3 +164,37 @@ fn format_conversation(responses: [String; 3]) -> String {
     }
     conversation
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn genre_examples_come_from_the_taxonomy() {
+        let genres = strings(&["Food", "Food > Baking", "Travel", "Science", "Sport"]);
+        assert_eq!(
+            genre_examples(&genres),
+            [
+                strings(&["Food"]),
+                strings(&["Food > Baking", "Travel"]),
+                strings(&["Food > Baking", "Sport"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn genre_examples_of_a_small_taxonomy() {
+        assert_eq!(
+            genre_examples(&strings(&["Food"])),
+            [strings(&["Food"]), strings(&["Food"]), strings(&["Food"])]
+        );
+        assert_eq!(
+            genre_examples(&[]),
+            [strings(&[]), strings(&[]), strings(&[])]
+        );
+    }
+}
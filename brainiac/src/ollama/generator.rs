@@ -1,13 +1,28 @@
-use crate::model::{ArticleGenre, ResponseOutputType};
+use crate::chunk::{chunk_text, estimate_tokens};
+use crate::error::{BrainiacError, ModelContext};
+use crate::model::ResponseOutputType;
+use crate::title::MAX_TITLE_WORDS;
+use ollama_rs::error::OllamaError;
 use ollama_rs::generation::completion::request::GenerationRequest;
 use ollama_rs::generation::completion::GenerationResponse;
 use ollama_rs::Ollama;
-use strum::VariantArray;
+use std::sync::Arc;
+use tokio_stream::StreamExt;
+
+/// Called with every token of a field while the model generates it
+pub type TokenCallback = Arc<dyn Fn(ResponseOutputType, &str) + Send + Sync>;
+
+/// The system prompt used for generating fields when none is set
+pub const DEFAULT_SYSTEM_PROMPT: &str =
+    "You are an editor at major publishing company. The following article has just arrived at your desk.";
 
 pub struct OutputGenerator<'a> {
     instance: &'a Ollama,
     content: Option<String>,
     model: String,
+    system: Option<String>,
+    on_token: Option<TokenCallback>,
+    genres: Vec<String>,
 }
 
 impl<'a> OutputGenerator<'a> {
@@ -16,6 +31,18 @@ impl<'a> OutputGenerator<'a> {
             instance,
             model,
             content: None,
+            system: None,
+            on_token: None,
+            genres: vec![],
+        }
+    }
+
+    fn context(&self, field: Option<ResponseOutputType>, prompt: String) -> ModelContext {
+        ModelContext {
+            model: self.model.clone(),
+            field,
+            prompt: Some(prompt),
+            path: None,
         }
     }
 
@@ -24,30 +51,113 @@ impl<'a> OutputGenerator<'a> {
         self.content = Some(content);
     }
 
+    /// System: Replaces the default system prompt used for generating fields
+    pub fn set_system(&mut self, system: String) {
+        self.system = Some(system);
+    }
+
+    /// Genres: The genre paths the model can choose from
+    pub fn set_genres(&mut self, genres: Vec<String>) {
+        self.genres = genres;
+    }
+
+    /// On token: Streams field generation, passing each token to the callback as it arrives
+    pub fn set_on_token(&mut self, on_token: TokenCallback) {
+        self.on_token = Some(on_token);
+    }
+
+    /// Condense content that is larger than `max_tokens` so it fits in the model context.
+    /// The content is split into chunks, each chunk is summarized and the summaries are joined
+    /// to replace the content. This repeats until the content fits or stops shrinking.
+    pub async fn condense_content(&mut self, max_tokens: usize) -> Result<(), BrainiacError> {
+        const MAX_ROUNDS: usize = 3;
+        let Some(mut content) = self.content.clone() else {
+            return Ok(());
+        };
+
+        for round in 0..MAX_ROUNDS {
+            let tokens = estimate_tokens(&content);
+            if tokens <= max_tokens {
+                break;
+            }
+            let chunks = chunk_text(&content, max_tokens);
+            log::info!(
+                "Content is ~{} tokens, summarizing {} chunks (round {})",
+                tokens,
+                chunks.len(),
+                round + 1
+            );
+            let mut summaries = vec![];
+            for (index, chunk) in chunks.iter().enumerate() {
+                let summary = self.summarize_chunk(chunk, index + 1, chunks.len()).await?;
+                log::trace!("Chunk {} Summary: {}\n", index + 1, summary.response);
+                summaries.push(summary.response.trim().to_string());
+            }
+            let condensed = summaries.join("\n\n");
+            if estimate_tokens(&condensed) >= tokens {
+                log::warn!("Chunk summaries did not reduce the content size, keeping the content");
+                break;
+            }
+            content = condensed;
+        }
+        let tokens = estimate_tokens(&content);
+        if tokens > max_tokens {
+            log::warn!(
+                "Content is still ~{} tokens after condensing, above the threshold of {}, the model may truncate it",
+                tokens,
+                max_tokens
+            );
+        }
+
+        self.content = Some(content);
+        Ok(())
+    }
+
+    async fn summarize_chunk(
+        &self,
+        chunk: &str,
+        position: usize,
+        total: usize,
+    ) -> Result<GenerationResponse, BrainiacError> {
+        let system = format!(
+            "{}\nThe following is part {} of {} of the article.",
+            self.system.as_deref().unwrap_or(DEFAULT_SYSTEM_PROMPT),
+            position,
+            total
+        );
+        let prompt = "Summarize this part of the article in a single paragraph. Keep the key facts, names and arguments.".to_string();
+        let request = GenerationRequest::new(self.model.clone(), prompt.clone())
+            .system(format!("{}\n###Article Part:\n{}", system, chunk));
+
+        self.instance
+            .generate(request)
+            .await
+            .map_err(|error| BrainiacError::OllamaDetailedError(error, self.context(None, prompt)))
+    }
+
     pub async fn generate_output(
         &self,
         output: ResponseOutputType,
-    ) -> ollama_rs::error::Result<GenerationResponse> {
-        let system = "You are an editor at major publishing company. The following article has just arrived at your desk.";
+    ) -> Result<GenerationResponse, BrainiacError> {
+        let content = self.content.as_ref().ok_or(BrainiacError::ContentNotSet)?;
+        let system = self.system.as_deref().unwrap_or(DEFAULT_SYSTEM_PROMPT);
         let guidelines = match output {
             ResponseOutputType::Title => "What should be the title of this article?",
             ResponseOutputType::Description => "Provide a brief summary of this article.",
-            ResponseOutputType::Genre => "What genre does this article belong to?",
+            ResponseOutputType::Genre => "What genres does this article belong to?",
             ResponseOutputType::Keywords => "What are some keywords that describe this article?",
         };
         let limitations = match output {
-            ResponseOutputType::Title => "The title should be at most 10 words.".to_string(),
+            ResponseOutputType::Title => {
+                format!("The title should be at most {} words.", MAX_TITLE_WORDS)
+            }
             ResponseOutputType::Description => {
                 "The summary should be less than 5 sentences in length and be written in a single paragraph.".to_string()
             }
             ResponseOutputType::Genre => {
                 format!(
-                    "The genre should be a single word and be one of these available options: {}",
-                    ArticleGenre::VARIANTS
-                        .iter()
-                        .map(|v| v.to_string())
-                        .collect::<Vec<String>>()
-                        .join(", ")
+                    "The genres should be a comma separated list of at most 3 of these available options, most relevant first: {}",
+                    self.genres.join(", ")
                 )
             }
             ResponseOutputType::Keywords => {
@@ -55,16 +165,38 @@ impl<'a> OutputGenerator<'a> {
             }
         };
 
-        let request = GenerationRequest::new(
-            self.model.clone(),
-            format!("{}\n{}", guidelines, limitations),
-        )
-        .system(format!(
-            "{}\n###Article:\n{}",
-            system,
-            self.content.clone().expect("Content not set")
-        ));
-
-        self.instance.generate(request).await
+        let prompt = format!("{}\n{}", guidelines, limitations);
+        let request = GenerationRequest::new(self.model.clone(), prompt.clone())
+            .system(format!("{}\n###Article:\n{}", system, content));
+
+        self.generate(request, output).await.map_err(|error| {
+            BrainiacError::OllamaDetailedError(error, self.context(Some(output), prompt))
+        })
+    }
+
+    /// Generate the full response, streaming it to the token callback when one is set.
+    async fn generate(
+        &self,
+        request: GenerationRequest<'_>,
+        output: ResponseOutputType,
+    ) -> Result<GenerationResponse, OllamaError> {
+        let Some(on_token) = &self.on_token else {
+            return self.instance.generate(request).await;
+        };
+        let mut stream = self.instance.generate_stream(request).await?;
+        let mut text = String::new();
+        let mut last = None;
+        while let Some(chunk) = stream.next().await {
+            for response in chunk? {
+                on_token(output, &response.response);
+                text.push_str(&response.response);
+                last = Some(response);
+            }
+        }
+        let mut response = last.ok_or(OllamaError::Other(
+            "the model returned no response".to_string(),
+        ))?;
+        response.response = text;
+        Ok(response)
     }
 }
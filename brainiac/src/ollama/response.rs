@@ -1,5 +1,3 @@
-use std::fmt::Display;
-
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,15 +23,51 @@ impl From<&str> for JSONResponse<String> {
     }
 }
 
-impl Display for JSONResponse<String> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let json = serde_json::to_string(&self).expect("Failed to serialize JSONResponse");
-        write!(f, "{}", json)
+impl<T: Serialize> JSONResponse<T> {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self)
     }
 }
-impl Display for JSONResponse<Vec<String>> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let json = serde_json::to_string(&self).expect("Failed to serialize JSONResponse");
-        write!(f, "{}", json)
+
+fn parse_value(output: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str::<JSONResponse<serde_json::Value>>(output)
+        .map(|json| json.response)
+        .map_err(|error| format!("expected a JSON object with a response field: {}", error))
+}
+
+/// Read the `response` field of a formatted model output as a single non-empty string.
+pub fn parse_text(output: &str) -> Result<String, String> {
+    match parse_value(output)? {
+        serde_json::Value::String(text) if !text.trim().is_empty() => Ok(text.trim().to_string()),
+        serde_json::Value::String(_) => Err("the response is empty".to_string()),
+        other => Err(format!(
+            "expected the response to be a string, got {}",
+            other
+        )),
+    }
+}
+
+/// Read the `response` field of a formatted model output as a list of strings.
+/// A single string response is accepted as a list with one item.
+pub fn parse_list(output: &str) -> Result<Vec<String>, String> {
+    let items = match parse_value(output)? {
+        serde_json::Value::Array(values) => values
+            .into_iter()
+            .map(|value| match value {
+                serde_json::Value::String(item) => Ok(item),
+                other => Err(format!("expected a list of strings, got {}", other)),
+            })
+            .collect::<Result<Vec<String>, String>>()?,
+        serde_json::Value::String(item) => vec![item],
+        other => return Err(format!("expected the response to be a list, got {}", other)),
+    };
+    let items = items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect::<Vec<String>>();
+    if items.is_empty() {
+        return Err("the response is empty".to_string());
     }
+    Ok(items)
 }
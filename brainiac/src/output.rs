@@ -0,0 +1,342 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::{BrainiacError, FileOperation};
+
+/// What to do when the output file already exists
+#[derive(
+    Default, Clone, Copy, PartialEq, strum::Display, strum::EnumString, strum::VariantNames,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// Return `BrainiacError::OutputCollision`
+    #[default]
+    Fail,
+    /// Replace the existing file
+    Overwrite,
+    /// Leave the existing file untouched
+    Skip,
+    /// Write to the first free `<name>-<n>.md` path
+    Suffix,
+}
+
+/// The result of writing an output file
+#[derive(Clone, Copy, PartialEq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum WriteStatus {
+    Created,
+    Overwritten,
+    Skipped,
+    /// Nothing was written because of a dry run
+    DryRun,
+    /// Nothing was written, the document is returned to be streamed by the caller
+    Streamed,
+}
+
+fn suffixed_path(path: &Path, suffix: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, suffix),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Decide where the output is written and how, following the collision `policy`.
+pub fn resolve_output_path(
+    path: PathBuf,
+    policy: CollisionPolicy,
+) -> Result<(PathBuf, WriteStatus), BrainiacError> {
+    if !path.exists() {
+        return Ok((path, WriteStatus::Created));
+    }
+    match policy {
+        CollisionPolicy::Fail => Err(BrainiacError::OutputCollision(path)),
+        CollisionPolicy::Overwrite => Ok((path, WriteStatus::Overwritten)),
+        CollisionPolicy::Skip => Ok((path, WriteStatus::Skipped)),
+        CollisionPolicy::Suffix => {
+            let path = (1..)
+                .map(|suffix| suffixed_path(&path, suffix))
+                .find(|candidate| !candidate.exists())
+                .unwrap_or(path);
+            Ok((path, WriteStatus::Created))
+        }
+    }
+}
+
+/// Where the original file is kept before it is replaced
+#[derive(Clone, PartialEq)]
+pub enum Backup {
+    /// A `<file>.bak` file next to the original
+    Sibling,
+    /// A copy inside the directory, named after a hash of the full path of the original so
+    /// files with the same name in different directories keep separate backups
+    Directory(PathBuf),
+}
+
+/// A short hex hash of the absolute path of `path`.
+fn path_hash(path: &Path) -> String {
+    use sha2::{Digest, Sha256};
+    let path = std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or(path.to_path_buf());
+    Sha256::digest(path.to_string_lossy().as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl Backup {
+    /// The backup path of the file at `path`.
+    pub fn path_for(&self, path: &Path) -> PathBuf {
+        let file_name = path.file_name().unwrap_or_default();
+        match self {
+            Backup::Sibling => {
+                let mut backup_name = file_name.to_os_string();
+                backup_name.push(".bak");
+                path.with_file_name(backup_name)
+            }
+            Backup::Directory(directory) => directory.join(format!(
+                "{}-{}",
+                path_hash(path),
+                file_name.to_string_lossy()
+            )),
+        }
+    }
+}
+
+/// A temporary file in `directory`. New files get the default permissions of the process,
+/// rather than the owner only permissions of temporary files.
+fn temp_file(directory: &Path) -> std::io::Result<tempfile::NamedTempFile> {
+    let mut builder = tempfile::Builder::new();
+    // The umask is applied to the mode when the file is created
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+    builder.tempfile_in(directory)
+}
+
+/// Write `contents` to `path` through a temporary file in the same directory that is renamed
+/// over the destination, so a crash never leaves a partially written file behind.
+/// When `overwrite` is false an existing file at `path` causes `BrainiacError::OutputCollision`.
+fn write_atomic(path: &Path, contents: &[u8], overwrite: bool) -> Result<(), BrainiacError> {
+    let directory = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut file = temp_file(directory).map_err(BrainiacError::file(FileOperation::Write, path))?;
+    // A replaced file keeps its permissions
+    if let Ok(metadata) = std::fs::metadata(path) {
+        file.as_file()
+            .set_permissions(metadata.permissions())
+            .map_err(BrainiacError::file(FileOperation::Write, path))?;
+    }
+    file.write_all(contents)
+        .and_then(|_| file.as_file().sync_all())
+        .map_err(BrainiacError::file(FileOperation::Write, path))?;
+    let persisted = if overwrite {
+        file.persist(path)
+    } else {
+        file.persist_noclobber(path)
+    };
+    match persisted {
+        Ok(_) => Ok(()),
+        Err(error) if error.error.kind() == std::io::ErrorKind::AlreadyExists => {
+            Err(BrainiacError::OutputCollision(path.to_path_buf()))
+        }
+        Err(error) => Err(BrainiacError::file(FileOperation::Write, path)(error.error)),
+    }
+}
+
+/// Write `contents` to a resolved output `path`.
+/// When an existing file is overwritten and `backup` is set, the original is copied there first.
+/// An existing backup is kept, so it always holds the file from before the first overwrite.
+pub fn write_output(
+    path: &Path,
+    contents: &str,
+    status: WriteStatus,
+    backup: Option<&Backup>,
+) -> Result<(), BrainiacError> {
+    match status {
+        WriteStatus::Created => write_atomic(path, contents.as_bytes(), false),
+        WriteStatus::Overwritten => {
+            if let Some(backup) = backup {
+                let backup_path = backup.path_for(path);
+                if let Some(directory) = backup_path.parent() {
+                    std::fs::create_dir_all(directory)
+                        .map_err(BrainiacError::file(FileOperation::Backup, directory))?;
+                }
+                let original = std::fs::read(path)
+                    .map_err(BrainiacError::file(FileOperation::Backup, path))?;
+                match write_atomic(&backup_path, &original, false) {
+                    Ok(()) => {
+                        log::info!("Backed up {} to {}", path.display(), backup_path.display())
+                    }
+                    Err(BrainiacError::OutputCollision(_)) => log::info!(
+                        "Keeping the existing backup {} of {}",
+                        backup_path.display(),
+                        path.display()
+                    ),
+                    Err(error) => return Err(error),
+                }
+            }
+            write_atomic(path, contents.as_bytes(), true)
+        }
+        WriteStatus::Skipped | WriteStatus::DryRun | WriteStatus::Streamed => Ok(()),
+    }
+}
+
+/// Replace the file at `path` with its backup and remove the backup.
+/// Returns the path of the backup that was restored.
+pub fn restore_backup(path: &Path, backup: &Backup) -> Result<PathBuf, BrainiacError> {
+    let backup_path = backup.path_for(path);
+    let original = std::fs::read(&backup_path)
+        .map_err(BrainiacError::file(FileOperation::Restore, &backup_path))?;
+    write_atomic(path, &original, true)?;
+    std::fs::remove_file(&backup_path)
+        .map_err(BrainiacError::file(FileOperation::Restore, &backup_path))?;
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn missing_files_are_created() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("article.md");
+        let (resolved, status) = resolve_output_path(path.clone(), CollisionPolicy::Fail).unwrap();
+        assert!(resolved == path && status == WriteStatus::Created);
+        write_output(&resolved, "new", status, None).unwrap();
+        assert_eq!(read(&path), "new");
+    }
+
+    #[test]
+    fn collisions_follow_the_policy() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("article.md");
+        std::fs::write(&path, "original").unwrap();
+        std::fs::write(directory.path().join("article-1.md"), "first").unwrap();
+
+        assert!(matches!(
+            resolve_output_path(path.clone(), CollisionPolicy::Fail),
+            Err(BrainiacError::OutputCollision(collision)) if collision == path
+        ));
+
+        let (resolved, status) = resolve_output_path(path.clone(), CollisionPolicy::Skip).unwrap();
+        assert!(resolved == path && status == WriteStatus::Skipped);
+        write_output(&resolved, "new", status, None).unwrap();
+        assert_eq!(read(&path), "original");
+
+        let (resolved, status) =
+            resolve_output_path(path.clone(), CollisionPolicy::Suffix).unwrap();
+        assert_eq!(resolved, directory.path().join("article-2.md"));
+        assert!(status == WriteStatus::Created);
+        write_output(&resolved, "new", status, None).unwrap();
+        assert_eq!(read(&resolved), "new");
+        assert_eq!(read(&path), "original");
+        assert_eq!(read(&directory.path().join("article-1.md")), "first");
+    }
+
+    #[test]
+    fn create_does_not_clobber_a_file_written_after_resolving() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("article.md");
+        let (resolved, status) = resolve_output_path(path.clone(), CollisionPolicy::Fail).unwrap();
+        std::fs::write(&path, "raced").unwrap();
+
+        assert!(matches!(
+            write_output(&resolved, "new", status, None),
+            Err(BrainiacError::OutputCollision(_))
+        ));
+        assert_eq!(read(&path), "raced");
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrite_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("article.md");
+        std::fs::write(&path, "original").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_output(&path, "new", WriteStatus::Overwritten, None).unwrap();
+        assert_eq!(read(&path), "new");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn first_backup_is_kept() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("article.md");
+        std::fs::write(&path, "original").unwrap();
+
+        write_output(
+            &path,
+            "first",
+            WriteStatus::Overwritten,
+            Some(&Backup::Sibling),
+        )
+        .unwrap();
+        write_output(
+            &path,
+            "second",
+            WriteStatus::Overwritten,
+            Some(&Backup::Sibling),
+        )
+        .unwrap();
+
+        let backup_path = directory.path().join("article.md.bak");
+        assert_eq!(Backup::Sibling.path_for(&path), backup_path);
+        assert_eq!(read(&path), "second");
+        assert_eq!(read(&backup_path), "original");
+    }
+
+    #[test]
+    fn directory_backups_keep_same_named_files_apart() {
+        let directory = tempfile::tempdir().unwrap();
+        let backup = Backup::Directory(directory.path().join("backups"));
+        let first = directory.path().join("one").join("article.md");
+        let second = directory.path().join("two").join("article.md");
+        for (path, contents) in [(&first, "one"), (&second, "two")] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+            write_output(path, "new", WriteStatus::Overwritten, Some(&backup)).unwrap();
+        }
+
+        assert_ne!(backup.path_for(&first), backup.path_for(&second));
+        assert_eq!(read(&backup.path_for(&first)), "one");
+        assert_eq!(read(&backup.path_for(&second)), "two");
+    }
+
+    #[test]
+    fn restore_puts_the_original_back() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("article.md");
+        std::fs::write(&path, "original").unwrap();
+        write_output(
+            &path,
+            "new",
+            WriteStatus::Overwritten,
+            Some(&Backup::Sibling),
+        )
+        .unwrap();
+
+        let backup_path = restore_backup(&path, &Backup::Sibling).unwrap();
+        assert_eq!(read(&path), "original");
+        assert!(!backup_path.exists());
+        assert!(matches!(
+            restore_backup(&path, &Backup::Sibling),
+            Err(BrainiacError::FileError(FileOperation::Restore, ..))
+        ));
+    }
+}
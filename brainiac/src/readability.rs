@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use crate::model::ReadabilityMetadata;
+
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '\'')
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .collect()
+}
+
+/// Count the sentences in `text`, ending them at terminal punctuation or a line break.
+pub(crate) fn sentence_count(text: &str) -> usize {
+    text.split(['.', '!', '?', '\n'])
+        .filter(|sentence| sentence.chars().any(char::is_alphabetic))
+        .count()
+}
+
+/// Approximate the syllables in an english word by counting groups of vowels.
+fn syllable_count(word: &str) -> usize {
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Score the readability of the prose of an article.
+/// Returns `None` when there is not enough text to score.
+pub fn score(prose: &str) -> Option<ReadabilityMetadata> {
+    let words = words(prose);
+    let sentences = sentence_count(prose);
+    if words.is_empty() || sentences == 0 {
+        return None;
+    }
+
+    let word_count = words.len() as f64;
+    let syllables = words.iter().map(|w| syllable_count(w)).sum::<usize>() as f64;
+    let unique_words = words.iter().collect::<HashSet<&String>>().len() as f64;
+    let words_per_sentence = word_count / sentences as f64;
+    let syllables_per_word = syllables / word_count;
+
+    Some(ReadabilityMetadata {
+        flesch_reading_ease: round(
+            206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+        ),
+        flesch_kincaid_grade: round(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59),
+        average_sentence_length: round(words_per_sentence),
+        lexical_diversity: round(unique_words / word_count),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences_end_at_punctuation_and_line_breaks() {
+        assert_eq!(sentence_count("One. Two! Three? Four"), 4);
+        assert_eq!(sentence_count("A heading\nA line."), 2);
+        assert_eq!(sentence_count("Version 1.2 is out..."), 2);
+        assert_eq!(sentence_count(""), 0);
+        assert_eq!(sentence_count("42. 7!"), 0);
+    }
+
+    #[test]
+    fn syllables_count_vowel_groups() {
+        assert_eq!(syllable_count("cat"), 1);
+        assert_eq!(syllable_count("table"), 2);
+        assert_eq!(syllable_count("make"), 1);
+        assert_eq!(syllable_count("reading"), 2);
+        assert_eq!(syllable_count("rhythm"), 1);
+    }
+
+    #[test]
+    fn scores_a_known_text() {
+        // Published Flesch reading ease of 37.5 for this sentence
+        let metadata = score(
+            "The Australian platypus is seemingly a hybrid of a mammal and reptilian creature.",
+        )
+        .unwrap();
+        assert!((metadata.flesch_reading_ease - 37.5).abs() < 10.0);
+        assert_eq!(metadata.average_sentence_length, 13.0);
+        assert_eq!(metadata.lexical_diversity, 0.92);
+
+        let metadata = score("The cat sat on the mat. The dog sat on the log.").unwrap();
+        assert_eq!(metadata.flesch_reading_ease, 116.15);
+        assert_eq!(metadata.average_sentence_length, 6.0);
+    }
+
+    #[test]
+    fn too_little_text_is_not_scored() {
+        assert!(score("").is_none());
+        assert!(score("   \n  ").is_none());
+        assert!(score("42 17 3.5").is_none());
+    }
+}
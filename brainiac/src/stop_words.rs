@@ -0,0 +1,19 @@
+/// Short function words, they separate keyword phrases.
+pub(crate) const SHORT: [&str; 48] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "can", "did", "do", "for", "had",
+    "has", "he", "her", "his", "how", "i", "if", "in", "is", "it", "its", "may", "not", "of", "on",
+    "or", "our", "she", "so", "the", "to", "too", "up", "us", "was", "we", "what", "when", "who",
+    "why", "will", "with", "you", "your",
+];
+/// Longer function words, they are never key terms of an article.
+pub(crate) const LONG: [&str; 40] = [
+    "about", "after", "again", "also", "because", "been", "before", "being", "between", "both",
+    "could", "does", "doing", "down", "during", "each", "from", "further", "have", "having",
+    "here", "into", "just", "more", "most", "only", "other", "over", "same", "should", "some",
+    "such", "than", "that", "their", "them", "then", "there", "these", "this",
+];
+
+/// Whether a lower case `word` is a stop word.
+pub(crate) fn is_stop_word(word: &str) -> bool {
+    SHORT.contains(&word) || LONG.contains(&word)
+}
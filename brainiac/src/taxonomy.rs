@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use strum::VariantArray;
+
+use crate::model::ArticleGenre;
+
+/// Separates the levels of a genre path, such as `Technology > Programming > Rust`.
+pub const GENRE_SEPARATOR: &str = " > ";
+/// The confidence of a label that names a genre through the synonym table.
+const SYNONYM_CONFIDENCE: f64 = 0.9;
+/// Fuzzy matches below this confidence are not matched at all.
+const MIN_CONFIDENCE: f64 = 0.6;
+/// Matches below this confidence are matched with a warning.
+pub const LOW_CONFIDENCE: f64 = 0.8;
+
+/// Common labels models use for the `ArticleGenre` variants
+const DEFAULT_SYNONYMS: [(&str, &str); 16] = [
+    ("tech", "Technology"),
+    ("software", "Technology"),
+    ("programming", "Technology"),
+    ("wellness", "Health"),
+    ("health and wellness", "Health"),
+    ("fitness", "Health"),
+    ("finance", "Business"),
+    ("economics", "Business"),
+    ("sci fi", "Entertainment"),
+    ("science fiction", "Entertainment"),
+    ("movies", "Entertainment"),
+    ("music", "Entertainment"),
+    ("cooking", "Food"),
+    ("recipes", "Food"),
+    ("current events", "News"),
+    ("editorial", "Opinion"),
+];
+
+/// A genre with the other names it is known by and its sub genres
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Genre {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub children: Vec<Genre>,
+}
+
+impl Genre {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            aliases: vec![],
+            children: vec![],
+        }
+    }
+}
+
+/// The genre a generated label was matched to
+#[derive(Clone, Serialize)]
+pub struct GenreMatch {
+    /// The label as it was generated
+    pub label: String,
+    /// The full path of the matched genre
+    pub genre: String,
+    /// 1 for an exact match, lower for synonyms and fuzzy matches
+    pub confidence: f64,
+}
+
+/// The genres an article can be classified under.
+/// The default taxonomy is the flat list of `ArticleGenre` variants.
+#[derive(Clone)]
+pub struct Taxonomy {
+    genres: Vec<Genre>,
+    /// Normalized labels and the genre they stand for
+    synonyms: HashMap<String, String>,
+}
+
+impl Default for Taxonomy {
+    fn default() -> Self {
+        Self::new(
+            ArticleGenre::VARIANTS
+                .iter()
+                .map(|genre| Genre::new(genre.to_string()))
+                .collect(),
+        )
+    }
+}
+
+/// Lowercase `label`, replace punctuation with spaces and normalize the spacing of words and
+/// path separators, so `Health & Wellness.` and `health wellness` are the same label.
+fn normalize(label: &str) -> String {
+    label
+        .split('>')
+        .map(|level| {
+            level
+                .to_lowercase()
+                .replace('&', " and ")
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .collect::<Vec<String>>()
+        .join(GENRE_SEPARATOR)
+}
+
+/// The number of single character edits that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The similarity of two labels from 0 to 1, based on their edit distance.
+pub(crate) fn similarity(a: &str, b: &str) -> f64 {
+    let length = a.chars().count().max(b.chars().count());
+    match length {
+        0 => 1.0,
+        length => 1.0 - edit_distance(a, b) as f64 / length as f64,
+    }
+}
+
+impl Taxonomy {
+    pub fn new(genres: Vec<Genre>) -> Self {
+        let synonyms = DEFAULT_SYNONYMS
+            .iter()
+            .map(|(label, genre)| (normalize(label), genre.to_string()))
+            .collect();
+        Self { genres, synonyms }
+    }
+
+    /// Add labels that stand for a genre, replacing the default synonyms with the same label.
+    pub fn with_synonyms(mut self, synonyms: &HashMap<String, String>) -> Self {
+        for (label, genre) in synonyms {
+            self.synonyms.insert(normalize(label), genre.clone());
+        }
+        self
+    }
+
+    /// Call `visit` with every genre and its full path, parents before their children.
+    fn walk<'a>(&'a self, mut visit: impl FnMut(&'a Genre, &str) -> bool) -> bool {
+        fn walk_level<'a>(
+            genres: &'a [Genre],
+            parent: Option<&str>,
+            visit: &mut impl FnMut(&'a Genre, &str) -> bool,
+        ) -> bool {
+            genres.iter().any(|genre| {
+                let path = match parent {
+                    Some(parent) => format!("{}{}{}", parent, GENRE_SEPARATOR, genre.name),
+                    None => genre.name.clone(),
+                };
+                visit(genre, &path) || walk_level(&genre.children, Some(&path), visit)
+            })
+        }
+        walk_level(&self.genres, None, &mut visit)
+    }
+
+    /// The full path of every genre, parents before their children.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = vec![];
+        self.walk(|_, path| {
+            paths.push(path.to_string());
+            false
+        });
+        paths
+    }
+
+    /// The full path of the genre a normalized `label` names exactly, by its path, name or one
+    /// of its aliases. The first genre in the taxonomy wins.
+    fn find(&self, label: &str) -> Option<String> {
+        let mut found = None;
+        self.walk(|genre, path| {
+            let matches = normalize(path) == label
+                || normalize(&genre.name) == label
+                || genre.aliases.iter().any(|alias| normalize(alias) == label);
+            if matches {
+                found = Some(path.to_string());
+            }
+            matches
+        });
+        found
+    }
+
+    /// Whether `label` names a genre exactly by its path, name or one of its aliases.
+    pub fn contains(&self, label: &str) -> bool {
+        self.find(&normalize(label)).is_some()
+    }
+
+    /// The genre `label` refers to. Labels are matched ignoring case and punctuation, first
+    /// exactly, then through the synonym table and last to the genre with the most similar
+    /// path, name or alias.
+    pub fn resolve(&self, label: &str) -> Option<GenreMatch> {
+        let normalized = normalize(label);
+        let matched = |genre: String, confidence: f64| GenreMatch {
+            label: label.to_string(),
+            genre,
+            confidence,
+        };
+        if let Some(genre) = self.find(&normalized) {
+            return Some(matched(genre, 1.0));
+        }
+        let synonym = self
+            .synonyms
+            .get(&normalized)
+            .and_then(|genre| self.find(&normalize(genre)));
+        if let Some(genre) = synonym {
+            return Some(matched(genre, SYNONYM_CONFIDENCE));
+        }
+        let mut best: Option<(String, f64)> = None;
+        self.walk(|genre, path| {
+            let names = [path, genre.name.as_str()]
+                .into_iter()
+                .chain(genre.aliases.iter().map(String::as_str));
+            for name in names {
+                let score = similarity(&normalize(name), &normalized);
+                if best.as_ref().is_none_or(|(_, best)| score > *best) {
+                    best = Some((path.to_string(), score));
+                }
+            }
+            false
+        });
+        best.filter(|(_, confidence)| *confidence >= MIN_CONFIDENCE)
+            .map(|(genre, confidence)| matched(genre, (confidence * 100.0).round() / 100.0))
+    }
+
+    /// Resolve every label, returning the matches for distinct genres in order and the labels
+    /// that matched no genre.
+    pub fn classify(&self, labels: &[String]) -> (Vec<GenreMatch>, Vec<String>) {
+        let mut matches: Vec<GenreMatch> = vec![];
+        let mut unmatched = vec![];
+        for label in labels {
+            match self.resolve(label) {
+                Some(matched) if !matches.iter().any(|m| m.genre == matched.genre) => {
+                    matches.push(matched)
+                }
+                Some(_) => {}
+                None => unmatched.push(label.clone()),
+            }
+        }
+        (matches, unmatched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taxonomy() -> Taxonomy {
+        let mut programming = Genre::new("Programming");
+        programming.children = vec![Genre::new("Rust")];
+        let mut technology = Genre::new("Technology");
+        technology.aliases = vec!["IT".to_string()];
+        technology.children = vec![programming];
+        Taxonomy::new(vec![technology, Genre::new("Health"), Genre::new("Food")])
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_ignores_case_punctuation_and_spacing() {
+        assert_eq!(normalize("Health & Wellness."), "health and wellness");
+        assert_eq!(normalize("  Sci-Fi "), "sci fi");
+        assert_eq!(
+            normalize("technology>programming >  Rust"),
+            "technology > programming > rust"
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("technology", "tecnology"), 1);
+        assert_eq!(edit_distance("", "food"), 4);
+    }
+
+    #[test]
+    fn similarity_is_relative_to_the_longest_label() {
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("food", "food"), 1.0);
+        assert_eq!(similarity("food", "good"), 0.75);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+    }
+
+    #[test]
+    fn paths_list_parents_before_children() {
+        assert_eq!(
+            taxonomy().paths(),
+            strings(&[
+                "Technology",
+                "Technology > Programming",
+                "Technology > Programming > Rust",
+                "Health",
+                "Food",
+            ])
+        );
+    }
+
+    #[test]
+    fn resolve_matches_paths_names_and_aliases_exactly() {
+        let taxonomy = taxonomy();
+        for (label, genre) in [
+            ("technology.", "Technology"),
+            ("it", "Technology"),
+            ("Rust", "Technology > Programming > Rust"),
+            ("technology>programming", "Technology > Programming"),
+        ] {
+            let matched = taxonomy.resolve(label).unwrap();
+            assert_eq!(matched.genre, genre);
+            assert_eq!(matched.confidence, 1.0);
+            assert_eq!(matched.label, label);
+        }
+    }
+
+    #[test]
+    fn resolve_uses_the_synonym_table() {
+        let matched = taxonomy().resolve("Health & Wellness.").unwrap();
+        assert_eq!(matched.genre, "Health");
+        assert_eq!(matched.confidence, SYNONYM_CONFIDENCE);
+
+        let matched = Taxonomy::default().resolve("Sci-Fi").unwrap();
+        assert_eq!(matched.genre, "ENTERTAINMENT");
+    }
+
+    #[test]
+    fn configured_synonyms_replace_the_defaults() {
+        let synonyms = HashMap::from([("Recipes".to_string(), "Health".to_string())]);
+        let matched = taxonomy()
+            .with_synonyms(&synonyms)
+            .resolve("recipes")
+            .unwrap();
+        assert_eq!(matched.genre, "Health");
+    }
+
+    #[test]
+    fn synonyms_for_missing_genres_are_ignored() {
+        // The default synonym for music is Entertainment, which is not in this taxonomy
+        assert!(taxonomy().resolve("music").is_none());
+    }
+
+    #[test]
+    fn resolve_falls_back_to_the_most_similar_genre() {
+        let taxonomy = taxonomy();
+        let matched = taxonomy.resolve("Tecnology").unwrap();
+        assert_eq!(matched.genre, "Technology");
+        assert_eq!(matched.confidence, 0.9);
+
+        let matched = taxonomy.resolve("Hlth").unwrap();
+        assert_eq!(matched.genre, "Health");
+        assert!(matched.confidence < LOW_CONFIDENCE);
+        assert!(matched.confidence >= MIN_CONFIDENCE);
+
+        assert!(taxonomy.resolve("Quantum Gardening").is_none());
+    }
+
+    #[test]
+    fn contains_only_accepts_exact_names() {
+        let taxonomy = taxonomy();
+        assert!(taxonomy.contains("technology > programming"));
+        assert!(taxonomy.contains("IT"));
+        assert!(!taxonomy.contains("Tecnology"));
+    }
+
+    #[test]
+    fn classify_removes_duplicate_genres_and_keeps_unmatched_labels() {
+        let (matches, unmatched) = taxonomy().classify(&strings(&[
+            "Technology",
+            "tech",
+            "IT",
+            "Health",
+            "Quantum Gardening",
+        ]));
+        let genres = matches
+            .iter()
+            .map(|matched| matched.genre.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(genres, ["Technology", "Health"]);
+        assert_eq!(unmatched, strings(&["Quantum Gardening"]));
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::stop_words;
+
+/// The number of most frequent article terms a title is expected to cover.
+const KEY_TERM_COUNT: usize = 10;
+/// Titles with fewer words read like labels rather than titles.
+const MIN_TITLE_WORDS: usize = 3;
+/// The maximum number of words in a title, asked for in the generation prompt and checked by lint.
+pub(crate) const MAX_TITLE_WORDS: usize = 10;
+/// The score lost for every word outside the expected title length.
+const LENGTH_PENALTY: f64 = 0.1;
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The most frequent content words of the article, most frequent first.
+pub fn key_terms(content: &str) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for word in words(content) {
+        if word.chars().count() > 3 && !stop_words::is_stop_word(&word) {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+    let mut terms = counts.into_iter().collect::<Vec<(String, usize)>>();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    terms
+        .into_iter()
+        .take(KEY_TERM_COUNT)
+        .map(|(term, _)| term)
+        .collect()
+}
+
+/// Score a title by the share of key terms it covers, minus a penalty for its length
+/// falling outside the expected range.
+fn score(title: &str, terms: &[String]) -> f64 {
+    let title_words = words(title).collect::<Vec<String>>();
+    let coverage = match terms.len() {
+        0 => 0.0,
+        total => {
+            terms
+                .iter()
+                .filter(|term| {
+                    title_words
+                        .iter()
+                        .any(|word| word.starts_with(term.as_str()))
+                })
+                .count() as f64
+                / total as f64
+        }
+    };
+    let length = title_words.len();
+    let outside = MIN_TITLE_WORDS.saturating_sub(length) + length.saturating_sub(MAX_TITLE_WORDS);
+    coverage - outside as f64 * LENGTH_PENALTY
+}
+
+/// Remove duplicate titles and order the rest from best to worst.
+pub fn rank(candidates: Vec<String>, terms: &[String]) -> Vec<String> {
+    let mut unique: Vec<String> = vec![];
+    for candidate in candidates {
+        if !unique
+            .iter()
+            .any(|title| title.eq_ignore_ascii_case(&candidate))
+        {
+            unique.push(candidate);
+        }
+    }
+    // The sort is stable, so equally scored titles keep their generation order
+    unique.sort_by(|a, b| score(b, terms).total_cmp(&score(a, terms)));
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn key_terms_skips_short_and_stop_words() {
+        let terms =
+            key_terms("The banana. Bananas and the banana, about this banana diet and diet");
+        assert_eq!(terms, strings(&["banana", "diet", "bananas"]));
+    }
+
+    #[test]
+    fn key_terms_of_prose_skip_code_and_images() {
+        let article = "Parsing tokens with a lexer.\n\n```rust\nlet lexer = Lexer::new();\n```\n\n```rust\nlexer.next();\n```\n\n![](diagram.png)\n\nThe lexer emits tokens.\n";
+        let terms = key_terms(&crate::markdown::analyze(article).prose);
+        assert_eq!(terms, strings(&["lexer", "tokens", "emits", "parsing"]));
+    }
+
+    #[test]
+    fn key_terms_are_capped() {
+        let content = (0..20)
+            .map(|index| format!("word{:02}", index))
+            .collect::<Vec<String>>()
+            .join(" ");
+        assert_eq!(key_terms(&content).len(), KEY_TERM_COUNT);
+    }
+
+    #[test]
+    fn rank_orders_by_key_term_coverage() {
+        let terms = strings(&["banana", "nutrition", "potassium"]);
+        let ranked = rank(
+            strings(&[
+                "A Walk Through the Market",
+                "Banana Nutrition and Potassium Explained",
+                "Why Bananas Matter for Your Health",
+            ]),
+            &terms,
+        );
+        assert_eq!(
+            ranked,
+            strings(&[
+                "Banana Nutrition and Potassium Explained",
+                "Why Bananas Matter for Your Health",
+                "A Walk Through the Market",
+            ])
+        );
+    }
+
+    #[test]
+    fn rank_penalizes_titles_outside_the_expected_length() {
+        let terms = strings(&["banana"]);
+        let ranked = rank(strings(&["Banana", "The Humble Banana"]), &terms);
+        assert_eq!(ranked, strings(&["The Humble Banana", "Banana"]));
+    }
+
+    #[test]
+    fn rank_removes_duplicates_and_keeps_ties_in_order() {
+        let ranked = rank(
+            strings(&["First Title Here", "first title here", "Second Title Here"]),
+            &[],
+        );
+        assert_eq!(ranked, strings(&["First Title Here", "Second Title Here"]));
+    }
+}
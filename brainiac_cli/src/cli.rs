@@ -1,6 +1,11 @@
-use std::path::PathBuf;
+use std::{net::SocketAddr, path::PathBuf};
 
-use clap::{builder::NonEmptyStringValueParser, value_parser, Parser, Subcommand};
+use brainiac::{output::CollisionPolicy, BrainiacGenerate};
+use clap::{
+    builder::{NonEmptyStringValueParser, PossibleValuesParser, TypedValueParser},
+    value_parser, ArgAction, Args, Parser, Subcommand, ValueEnum,
+};
+use strum::VariantNames;
 
 #[derive(Parser)]
 #[command(version, about="A tool to generate metadata for markdown files", long_about = None)]
@@ -8,29 +13,201 @@ use clap::{builder::NonEmptyStringValueParser, value_parser, Parser, Subcommand}
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet", help = "Log more detail, repeat for trace output. Overrides RUST_LOG")]
+    pub verbose: u8,
+    #[arg(short, long, global = true, action = ArgAction::Count, help = "Log less, -q for warnings, -qq for errors and -qqq for nothing. Overrides RUST_LOG")]
+    pub quiet: u8,
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text, help = "The format the result and errors are printed in")]
+    pub output_format: OutputFormat,
+    #[arg(long, global = true, help = "Shorthand for --output-format json")]
+    pub json: bool,
+    #[arg(long, global = true, help = "The path to a brainiac.toml file with the genre taxonomy", value_parser=value_parser!(PathBuf))]
+    pub config_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored human readable output
+    Text,
+    /// A pretty printed JSON document
+    Json,
+    /// A single JSON object per line
+    Ndjson,
+}
+
+impl Cli {
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.output_format
+        }
+    }
+
+    /// The log level requested by the verbosity flags, `None` when neither flag is set.
+    pub fn log_level(&self) -> Option<log::LevelFilter> {
+        match (self.verbose, self.quiet) {
+            (0, 0) => None,
+            (1, _) => Some(log::LevelFilter::Debug),
+            (_, 0) => Some(log::LevelFilter::Trace),
+            (_, 1) => Some(log::LevelFilter::Warn),
+            (_, 2) => Some(log::LevelFilter::Error),
+            (_, _) => Some(log::LevelFilter::Off),
+        }
+    }
+}
+
+/// The options shared by every command that generates metadata
+#[derive(Args)]
+pub struct GenerateArgs {
+    #[arg(
+        short,
+        long,
+        help = "The name of the model used for generating metadata"
+    )]
+    pub gen_model: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "The name of the model used for formatting model responses"
+    )]
+    pub format_model: Option<String>,
+    #[arg(short,long, help="The name of the file author", required=true, value_parser=NonEmptyStringValueParser::new())]
+    pub author: String,
+    #[arg(
+        short,
+        long,
+        help = "The estimated token count above which the article is summarized in chunks"
+    )]
+    pub chunk_threshold: Option<usize>,
+    #[arg(long, help = "Skip computing readability scores")]
+    pub no_readability: bool,
+}
+
+impl From<GenerateArgs> for BrainiacGenerate {
+    fn from(args: GenerateArgs) -> Self {
+        BrainiacGenerate {
+            gen_model: args.gen_model,
+            format_model: args.format_model,
+            author: args.author,
+            chunk_threshold: args.chunk_threshold,
+            readability: !args.no_readability,
+        }
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Generate the metdata for a markdown file")]
     Generate {
-        #[arg(short, long, help = "The path to the SOURCE markdown file", required = true, value_parser=value_parser!(PathBuf))]
+        #[arg(short, long, help = "The path to the SOURCE markdown file, - reads from stdin", required = true, value_parser=value_parser!(PathBuf))]
         source_path: PathBuf,
-        #[arg(short, long, help = "The path to the OUTPUT directory", required = true, value_parser=value_parser!(PathBuf))]
+        #[arg(short, long, help = "The path to the OUTPUT directory", required_unless_present_any = ["in_place", "stdout"], conflicts_with_all = ["in_place", "stdout"], value_parser=value_parser!(PathBuf))]
         output_dir_path: Option<PathBuf>,
+        #[command(flatten)]
+        generate: GenerateArgs,
+        #[arg(
+            long,
+            help = "What to do when the OUTPUT file already exists",
+            default_value_t = CollisionPolicy::Fail,
+            value_parser = PossibleValuesParser::new(CollisionPolicy::VARIANTS).try_map(|v| v.parse::<CollisionPolicy>())
+        )]
+        on_collision: CollisionPolicy,
+        #[arg(
+            long,
+            help = "Print the front matter and OUTPUT path without writing the file"
+        )]
+        dry_run: bool,
+        #[arg(
+            long,
+            help = "Replace the front matter of the SOURCE file instead of writing a new file"
+        )]
+        in_place: bool,
+        #[arg(
+            long,
+            conflicts_with = "in_place",
+            help = "Write the article with its front matter to stdout instead of a file"
+        )]
+        stdout: bool,
+        #[arg(
+            long,
+            requires = "stdout",
+            help = "Only write the front matter to stdout"
+        )]
+        metadata_only: bool,
+        #[arg(long, help = "Keep a .bak copy of any file that is replaced")]
+        backup: bool,
+        #[arg(long, help = "The directory to keep copies of replaced files in", value_parser=value_parser!(PathBuf))]
+        backup_dir_path: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Show the model output for each field on stderr as it is generated"
+        )]
+        stream: bool,
+        #[arg(
+            long,
+            help = "Accept, edit or regenerate each generated field before the file is written"
+        )]
+        interactive: bool,
         #[arg(
-            short,
             long,
-            help = "The name of the model used for generating metadata"
+            help = "How many titles to generate and rank, the others are kept as alternate titles",
+            default_value_t = 1
         )]
-        gen_model: Option<String>,
+        title_candidates: usize,
+    },
+    #[command(
+        about = "Watch a directory and update the metadata of markdown files when they are saved"
+    )]
+    Watch {
+        #[arg(short, long, help = "The path to the directory to watch", required = true, value_parser=value_parser!(PathBuf))]
+        dir_path: PathBuf,
+        #[command(flatten)]
+        generate: GenerateArgs,
         #[arg(
-            short,
             long,
-            help = "The name of the model used for formatting model responses"
+            help = "How long a file must be left unchanged before it is updated, in milliseconds",
+            default_value_t = 2000
         )]
-        format_model: Option<String>,
-        #[arg(short,long, help="The name of the file author", required=true, value_parser=NonEmptyStringValueParser::new())]
-        author: String,
+        debounce_ms: u64,
+        #[arg(long, help = "Keep a .bak copy of any file that is replaced")]
+        backup: bool,
+        #[arg(long, help = "The directory to keep copies of replaced files in", value_parser=value_parser!(PathBuf))]
+        backup_dir_path: Option<PathBuf>,
+    },
+    #[command(about = "Serve metadata generation, parsing and linting as an HTTP API")]
+    Serve {
+        #[arg(
+            long,
+            help = "The address to listen on",
+            default_value = "127.0.0.1:8080"
+        )]
+        address: SocketAddr,
+        #[command(flatten)]
+        generate: GenerateArgs,
+        #[arg(
+            long,
+            help = "How many articles are generated at once, further requests are queued",
+            default_value_t = 1
+        )]
+        concurrency: usize,
+        #[arg(
+            long,
+            help = "How long a metadata request may take, including waiting for a free slot, in seconds",
+            default_value_t = 300
+        )]
+        timeout_secs: u64,
+    },
+    #[command(about = "Run a language server for markdown files over stdin and stdout")]
+    Lsp {
+        #[command(flatten)]
+        generate: GenerateArgs,
+    },
+    #[command(about = "Restore a markdown file from its backup")]
+    Restore {
+        #[arg(short, long, help = "The path to the SOURCE markdown file", required = true, value_parser=value_parser!(PathBuf))]
+        source_path: PathBuf,
+        #[arg(long, help = "The directory the backup was kept in, defaults to the .bak file", value_parser=value_parser!(PathBuf))]
+        backup_dir_path: Option<PathBuf>,
     },
 }
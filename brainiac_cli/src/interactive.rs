@@ -0,0 +1,85 @@
+use std::io::{BufRead, Write};
+use std::process::Command;
+
+use brainiac::{model::ResponseOutputType, Review};
+use colored::Colorize;
+
+/// Open `value` in `$VISUAL` or `$EDITOR` and return the edited text,
+/// `None` when the editor could not be run, exited with an error or the text was cleared.
+fn edit(field: ResponseOutputType, value: &str) -> Option<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!("brainiac-{}-", field))
+        .suffix(".txt")
+        .tempfile()
+        .ok()?;
+    writeln!(file, "{}", value).ok()?;
+    // The editor may be set with arguments, such as `code --wait`
+    let mut arguments = editor.split_whitespace();
+    let status = Command::new(arguments.next()?)
+        .args(arguments)
+        .arg(file.path())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            eprintln!("{} exited with {}", editor, status);
+            return None;
+        }
+        Err(error) => {
+            eprintln!("Could not run {}: {}", editor, error);
+            return None;
+        }
+    }
+    let edited = std::fs::read_to_string(file.path()).ok()?;
+    let edited = edited.trim();
+    if edited.is_empty() {
+        eprintln!("The {} was left empty, nothing changed", field);
+        return None;
+    }
+    Some(edited.to_string())
+}
+
+/// Show a generated field and ask whether to accept, edit, regenerate it or pick an alternate.
+/// The prompt is written to stderr so stdout can still carry the result.
+pub fn review(field: ResponseOutputType, value: &str, alternates: &[String]) -> Review {
+    let stdin = std::io::stdin();
+    eprintln!("\n{}\n{}", field.to_string().bold(), value);
+    for (index, alternate) in alternates.iter().enumerate() {
+        eprintln!("{:>3}. {}", index + 1, alternate.dimmed());
+    }
+    let question = match alternates.len() {
+        0 => "[a]ccept, [e]dit or [r]egenerate?".to_string(),
+        count => format!("[a]ccept, [e]dit, [r]egenerate or pick 1-{}?", count),
+    };
+    loop {
+        eprint!("{} ", question.cyan());
+        let mut answer = String::new();
+        match stdin.lock().read_line(&mut answer) {
+            // Stdin was closed, keep the generated value
+            Ok(0) | Err(_) => return Review::Accept,
+            Ok(_) => {}
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "" | "a" | "accept" => return Review::Accept,
+            "r" | "regenerate" => return Review::Regenerate,
+            "e" | "edit" => {
+                if let Some(edited) = edit(field, value) {
+                    eprintln!("{}", edited);
+                    return Review::Replace(edited);
+                }
+            }
+            answer => {
+                let alternate = answer
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| alternates.get(number.checked_sub(1)?));
+                if let Some(alternate) = alternate {
+                    return Review::Replace(alternate.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use brainiac::{
+    lint::{LintIssue, LintRule, Severity},
+    model::ResponseOutputType,
+    Brainiac, GeneratedDocument,
+};
+use tower_lsp::jsonrpc::{Error, Result as RpcResult};
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+
+const GENERATE_COMMAND: &str = "brainiac.generateFrontMatter";
+const REGENERATE_COMMAND: &str = "brainiac.regenerateField";
+/// Every field that can be regenerated on its own, in the order the actions are offered
+const FIELDS: [ResponseOutputType; 4] = [
+    ResponseOutputType::Title,
+    ResponseOutputType::Description,
+    ResponseOutputType::Genre,
+    ResponseOutputType::Keywords,
+];
+
+/// The latest text of an open document, which may not be saved yet
+#[derive(Clone)]
+struct Document {
+    text: String,
+    version: i32,
+}
+
+struct Backend {
+    client: Client,
+    brainiac: Brainiac,
+    documents: Mutex<HashMap<Url, Document>>,
+}
+
+/// The range covering all of `text`.
+fn full_range(text: &str) -> Range {
+    let line = text.split('\n').count() - 1;
+    let character = text
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .encode_utf16()
+        .count();
+    Range::new(
+        Position::new(0, 0),
+        Position::new(line as u32, character as u32),
+    )
+}
+
+/// An edit turning `text` into `document` that only replaces the part before their common end,
+/// so the article body is left alone.
+fn front_matter_edit(text: &str, document: &str) -> TextEdit {
+    let common = text
+        .chars()
+        .rev()
+        .zip(document.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    let replaced = &text[..text.len() - common];
+    TextEdit::new(
+        Range::new(Position::new(0, 0), full_range(replaced).end),
+        document[..document.len() - common].to_string(),
+    )
+}
+
+/// The front matter key a lint issue is about.
+fn issue_key(issue: &LintIssue) -> Option<&'static str> {
+    match issue.rule {
+        LintRule::EmptyTitle | LintRule::LongTitle => Some("title"),
+        LintRule::SlugMismatch => Some("slug"),
+        LintRule::EmptyDescription | LintRule::LongDescription => Some("description"),
+        LintRule::MissingKeywords => Some("keywords"),
+        LintRule::MissingGenre | LintRule::UnknownGenre => Some("genres"),
+        LintRule::StaleAnalytics => Some("[analytics]"),
+        LintRule::StaleToc => Some("[[toc]]"),
+        LintRule::MissingFrontMatter | LintRule::InvalidFrontMatter => None,
+    }
+}
+
+/// The range of the front matter line that sets `key`, or the first line when there is none.
+fn key_range(text: &str, key: Option<&str>) -> Range {
+    let line_range = |index: usize, line: &str| {
+        Range::new(
+            Position::new(index as u32, 0),
+            Position::new(index as u32, line.encode_utf16().count() as u32),
+        )
+    };
+    let first_line = text.lines().next().unwrap_or_default();
+    let Some(key) = key.filter(|_| first_line.trim_end() == "---") else {
+        return line_range(0, first_line);
+    };
+    text.lines()
+        .enumerate()
+        .skip(1)
+        .take_while(|(_, line)| line.trim_end() != "---")
+        .find(|(_, line)| {
+            line.trim_start()
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '=']))
+        })
+        .map(|(index, line)| line_range(index, line))
+        .unwrap_or(line_range(0, first_line))
+}
+
+fn diagnostics(text: &str, issues: Vec<LintIssue>) -> Vec<Diagnostic> {
+    issues
+        .into_iter()
+        .map(|issue| Diagnostic {
+            range: key_range(text, issue_key(&issue)),
+            severity: Some(match issue.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+            }),
+            code: Some(NumberOrString::String(issue.rule.to_string())),
+            source: Some("brainiac".to_string()),
+            message: issue.message,
+            data: issue.field.map(|field| serde_json::json!(field)),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn action_title(field: ResponseOutputType) -> String {
+    match field {
+        ResponseOutputType::Keywords => "Suggest keywords".to_string(),
+        field => format!("Regenerate {}", field),
+    }
+}
+
+fn regenerate_action(
+    uri: &Url,
+    field: ResponseOutputType,
+    kind: CodeActionKind,
+    diagnostics: Option<Vec<Diagnostic>>,
+) -> CodeActionOrCommand {
+    let title = action_title(field);
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.clone(),
+        kind: Some(kind),
+        diagnostics,
+        command: Some(Command::new(
+            title,
+            REGENERATE_COMMAND.to_string(),
+            Some(vec![serde_json::json!(uri), serde_json::json!(field)]),
+        )),
+        ..Default::default()
+    })
+}
+
+impl Backend {
+    fn document(&self, uri: &Url) -> Option<Document> {
+        self.documents.lock().unwrap().get(uri).cloned()
+    }
+
+    async fn update(&self, uri: Url, text: String, version: i32) {
+        let diagnostics = match self.brainiac.lint(&text) {
+            Ok(issues) => diagnostics(&text, issues),
+            Err(error) => {
+                log::warn!("Could not lint {}: {}", uri, error);
+                vec![]
+            }
+        };
+        self.documents
+            .lock()
+            .unwrap()
+            .insert(uri.clone(), Document { text, version });
+        self.client
+            .publish_diagnostics(uri, diagnostics, Some(version))
+            .await;
+    }
+
+    /// Replace the front matter of the document with the generated one, or show the error to
+    /// the user. Generating takes a while, so the edit is dropped when the document was changed
+    /// in the meantime, and it is sent for the version it was generated from.
+    async fn apply(
+        &self,
+        uri: Url,
+        document: &Document,
+        generated: Result<GeneratedDocument, brainiac::error::BrainiacError>,
+    ) {
+        let generated = match generated {
+            Ok(generated) => generated,
+            Err(error) => {
+                self.client
+                    .show_message(MessageType::ERROR, error.to_string())
+                    .await;
+                return;
+            }
+        };
+        let current = self.document(&uri).map(|current| current.version);
+        if current != Some(document.version) {
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    "The document changed while the front matter was generated, try again",
+                )
+                .await;
+            return;
+        }
+        let edit = front_matter_edit(&document.text, &generated.document);
+        let edit = WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Edits(vec![TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri,
+                    version: Some(document.version),
+                },
+                edits: vec![OneOf::Left(edit)],
+            }])),
+            ..Default::default()
+        };
+        if let Err(error) = self.client.apply_edit(edit).await {
+            log::warn!("Could not apply the generated front matter: {}", error);
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, _: InitializeParams) -> RpcResult<InitializeResult> {
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![GENERATE_COMMAND.to_string(), REGENERATE_COMMAND.to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "brainiac".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn shutdown(&self) -> RpcResult<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.update(document.uri, document.text, document.version)
+            .await;
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // Full sync, the last change holds the whole document
+        if let Some(change) = params.content_changes.into_iter().last() {
+            self.update(
+                params.text_document.uri,
+                change.text,
+                params.text_document.version,
+            )
+            .await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.lock().unwrap().remove(&uri);
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> RpcResult<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let Some(document) = self.document(&uri) else {
+            return Ok(None);
+        };
+        let mut actions = vec![];
+        for diagnostic in params.context.diagnostics {
+            let field = diagnostic
+                .data
+                .clone()
+                .and_then(|data| serde_json::from_value::<String>(data).ok())
+                .and_then(|field| ResponseOutputType::from_str(&field).ok());
+            if let Some(field) = field {
+                actions.push(regenerate_action(
+                    &uri,
+                    field,
+                    CodeActionKind::QUICKFIX,
+                    Some(vec![diagnostic]),
+                ));
+            }
+        }
+        actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+            title: "Generate front matter".to_string(),
+            kind: Some(CodeActionKind::SOURCE),
+            command: Some(Command::new(
+                "Generate front matter".to_string(),
+                GENERATE_COMMAND.to_string(),
+                Some(vec![serde_json::json!(uri)]),
+            )),
+            ..Default::default()
+        }));
+        if matches!(self.brainiac.parse(&document.text), Ok(Some(_))) {
+            actions.extend(FIELDS.into_iter().map(|field| {
+                regenerate_action(&uri, field, CodeActionKind::REFACTOR_REWRITE, None)
+            }));
+        }
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> RpcResult<Option<serde_json::Value>> {
+        let mut arguments = params.arguments.into_iter();
+        let uri = arguments
+            .next()
+            .and_then(|uri| serde_json::from_value::<Url>(uri).ok())
+            .ok_or(Error::invalid_params(
+                "the first argument must be a document uri",
+            ))?;
+        let document = self
+            .document(&uri)
+            .ok_or(Error::invalid_params("the document is not open"))?;
+        let generated = match params.command.as_str() {
+            GENERATE_COMMAND => self.brainiac.generate_metadata(&document.text).await,
+            REGENERATE_COMMAND => {
+                let field = arguments
+                    .next()
+                    .and_then(|field| serde_json::from_value::<String>(field).ok())
+                    .and_then(|field| ResponseOutputType::from_str(&field).ok())
+                    .ok_or(Error::invalid_params("the second argument must be a field"))?;
+                self.brainiac.regenerate_field(&document.text, field).await
+            }
+            _ => return Err(Error::method_not_found()),
+        };
+        self.apply(uri, &document, generated).await;
+        Ok(None)
+    }
+}
+
+/// Run a language server for markdown files over stdin and stdout until the client exits.
+pub async fn lsp(brainiac: Brainiac) {
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        brainiac,
+        documents: Mutex::new(HashMap::new()),
+    });
+    Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+        .serve(service)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The byte offset of an LSP `position` in `text`.
+    fn offset(text: &str, position: Position) -> usize {
+        let line_start = text
+            .split_inclusive('\n')
+            .take(position.line as usize)
+            .map(str::len)
+            .sum::<usize>();
+        let mut units = 0;
+        let line = &text[line_start..];
+        let column = line
+            .char_indices()
+            .find(|(_, c)| {
+                let found = units >= position.character as usize;
+                units += c.len_utf16();
+                found
+            })
+            .map_or(line.len(), |(index, _)| index);
+        line_start + column
+    }
+
+    fn apply(text: &str, edit: &TextEdit) -> String {
+        let start = offset(text, edit.range.start);
+        let end = offset(text, edit.range.end);
+        format!("{}{}{}", &text[..start], edit.new_text, &text[end..])
+    }
+
+    #[test]
+    fn full_range_counts_utf16_units() {
+        assert_eq!(full_range("").end, Position::new(0, 0));
+        assert_eq!(full_range("one\ntwo\n").end, Position::new(2, 0));
+        assert_eq!(full_range("one\ncafé 😀").end, Position::new(1, 7));
+    }
+
+    #[test]
+    fn front_matter_edit_keeps_a_non_ascii_body() {
+        let body = "# Café ☕\n\nÜber 😀 emoji\n";
+        let text = format!("---\ntitle = \"Old\"\n---\n{}", body);
+        let document = format!("---\ntitle = \"Nouveau café\"\nslug = \"é\"\n---\n{}", body);
+        let edit = front_matter_edit(&text, &document);
+        assert_eq!(edit.range.end, Position::new(1, 12));
+        assert!(!edit.new_text.contains("Über"));
+        assert_eq!(apply(&text, &edit), document);
+    }
+
+    #[test]
+    fn front_matter_edit_without_a_trailing_newline() {
+        let text = "---\ntitle = \"Old\"\n---\nThe end 😀";
+        let document = "---\ntitle = \"New\"\n---\nThe end 😀";
+        let edit = front_matter_edit(text, document);
+        assert_eq!(edit.range.end, Position::new(1, 12));
+        assert_eq!(edit.new_text, "---\ntitle = \"New");
+        assert_eq!(apply(text, &edit), document);
+    }
+
+    #[test]
+    fn front_matter_edit_adds_missing_front_matter() {
+        let text = "Ünïcode body\nwithout front matter";
+        let document = format!("---\ntitle = \"New\"\n---\n{}", text);
+        let edit = front_matter_edit(text, &document);
+        assert_eq!(
+            edit.range,
+            Range::new(Position::new(0, 0), Position::new(0, 0))
+        );
+        assert_eq!(edit.new_text, "---\ntitle = \"New\"\n---\n");
+        assert_eq!(apply(text, &edit), document);
+
+        assert_eq!(front_matter_edit(text, text).new_text, "");
+    }
+
+    #[test]
+    fn key_range_finds_the_front_matter_line() {
+        let text = "---\ntitle = \"Café 😀\"\ngenres = []\n---\ntitle = body";
+        assert_eq!(
+            key_range(text, Some("title")),
+            Range::new(Position::new(1, 0), Position::new(1, 17))
+        );
+        assert_eq!(key_range(text, Some("genres")).start, Position::new(2, 0));
+        // Keys after the front matter and keys sharing a prefix are not matched
+        assert_eq!(key_range(text, Some("slug")).start, Position::new(0, 0));
+        assert_eq!(key_range(text, Some("genre")).start, Position::new(0, 0));
+    }
+
+    #[test]
+    fn key_range_without_front_matter_is_the_first_line() {
+        let text = "Ünïcode line\ntitle = \"Not front matter\"\n";
+        let first_line = Range::new(Position::new(0, 0), Position::new(0, 12));
+        assert_eq!(key_range(text, Some("title")), first_line);
+        assert_eq!(key_range(text, None), first_line);
+        assert_eq!(key_range("", Some("title")), Range::default());
+    }
+}
@@ -1,54 +1,366 @@
-use brainiac::{append_metadata, create_output_file_name, BrainiacAppend};
+use brainiac::{
+    config::Config,
+    error::{BrainiacError, ErrorCategory},
+    model::ResponseOutputType,
+    output::{Backup, WriteStatus},
+    restore_article, BrainiacAppend, BrainiacBuilder, BrainiacGenerate, BrainiacRestore,
+    InputSource, OutputTarget,
+};
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, OutputFormat};
 use colored::Colorize;
-use std::{error::Error, io::Write};
+use std::{io::Write, path::PathBuf, process::ExitCode, sync::Mutex, time::Duration};
 
 mod cli;
+mod interactive;
+mod lsp;
+mod serve;
+mod watch;
+
+fn backup_from_args(backup: bool, backup_dir_path: Option<PathBuf>) -> Option<Backup> {
+    match backup_dir_path {
+        Some(directory) => Some(Backup::Directory(directory)),
+        None if backup => Some(Backup::Sibling),
+        None => None,
+    }
+}
+
+/// The process exit code for each error category, so scripts can react to the kind of failure.
+/// Exit code 2 is left to clap for invalid arguments.
+fn exit_code(category: ErrorCategory) -> u8 {
+    match category {
+        ErrorCategory::Internal => 1,
+        ErrorCategory::Model => 3,
+        ErrorCategory::Io => 4,
+        ErrorCategory::FrontMatter => 5,
+        ErrorCategory::Validation => 6,
+        ErrorCategory::Config => 7,
+        ErrorCategory::Collision => 8,
+    }
+}
+
+fn print_json(value: serde_json::Value, format: OutputFormat) -> Result<(), BrainiacError> {
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    let json = match format {
+        OutputFormat::Ndjson => serde_json::to_string(&value)?,
+        _ => serde_json::to_string_pretty(&value)?,
+    };
+    writeln!(handle, "{}", json)?;
+    Ok(())
+}
+
+fn print_error(error: &BrainiacError, format: OutputFormat) {
+    let code = exit_code(error.category());
+    if format != OutputFormat::Text {
+        let _ = print_json(
+            serde_json::json!({
+            "error": {
+                "category": error.category().to_string(),
+                "message": error.to_string(),
+                "exit_code": code,
+            }
+            }),
+            format,
+        );
+    } else {
+        let stderr = std::io::stderr(); // get the global stderr entity
+        let mut handle = stderr.lock(); // acquire a lock on it
+        let _ = writeln!(handle, "{}", error.to_string().red());
+    }
+}
+
+fn milliseconds(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// Print every token to stderr as it is generated, with a heading for each field.
+fn print_token() -> impl Fn(ResponseOutputType, &str) + Send + Sync + 'static {
+    let current = Mutex::new(None);
+    move |field, token| {
+        let stderr = std::io::stderr();
+        let mut handle = stderr.lock();
+        let mut current = current.lock().unwrap();
+        if *current != Some(field) {
+            let _ = writeln!(handle, "\n{}", field.to_string().bold());
+            *current = Some(field);
+        }
+        let _ = write!(handle, "{}", token.dimmed());
+        let _ = handle.flush();
+    }
+}
+
+/// The client builder for a generating command, with the loaded `config` and optional `backup`.
+fn client_builder(
+    generate: BrainiacGenerate,
+    config: Config,
+    backup: Option<Backup>,
+) -> BrainiacBuilder {
+    let builder = BrainiacBuilder::from(generate).config(config);
+    match backup {
+        Some(backup) => builder.backup(backup),
+        None => builder,
+    }
+}
+
+async fn generate(
+    params: BrainiacAppend,
+    format: OutputFormat,
+    metadata_only: bool,
+    stream: bool,
+    interactive: bool,
+    title_candidates: usize,
+    config: Config,
+) -> Result<(), BrainiacError> {
+    if interactive && params.source.path().is_none() {
+        return Err(BrainiacError::ConfigError(
+            None,
+            "an article read from stdin can not be reviewed interactively".to_string(),
+        ));
+    }
+    let mut builder = client_builder(params.generate, config, params.backup)
+        .on_collision(params.on_collision)
+        .title_candidates(title_candidates);
+    if stream {
+        builder = builder.on_token(print_token());
+    }
+    if interactive {
+        builder = builder.on_review(interactive::review);
+    }
+    let output = builder
+        .build()?
+        .append(&params.source, &params.output, params.dry_run)
+        .await;
+    if stream {
+        eprintln!();
+    }
+    let output = output?;
+    let generated = output.generated;
+    if output.status == WriteStatus::Streamed {
+        return match (format, metadata_only) {
+            (OutputFormat::Text, true) => {
+                write!(std::io::stdout(), "{}", generated.rendered_metadata)?;
+                Ok(())
+            }
+            (OutputFormat::Text, false) => {
+                write!(std::io::stdout(), "{}", generated.document)?;
+                Ok(())
+            }
+            (_, true) => print_json(serde_json::to_value(&generated.metadata)?, format),
+            (_, false) => print_json(
+                serde_json::json!({
+                    "metadata": generated.metadata,
+                    "document": generated.document,
+                }),
+                format,
+            ),
+        };
+    }
+    if format != OutputFormat::Text {
+        let fields = generated
+            .timings
+            .fields
+            .iter()
+            .map(|(field, duration)| (field.to_string(), milliseconds(*duration).into()))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+        return print_json(
+            serde_json::json!({
+                "status": output.status.to_string(),
+                "file": output.output_path,
+                "metadata": generated.metadata,
+                "genre_matches": generated.genre_matches,
+                "unmatched_genres": generated.unmatched_genres,
+                "models": {
+                    "generation": generated.gen_model,
+                    "formatting": generated.format_model,
+                },
+                "timings_ms": {
+                    "condense": milliseconds(generated.timings.condense),
+                    "fields": fields,
+                    "total": milliseconds(generated.timings.total),
+                },
+            }),
+            format,
+        );
+    }
+    let stdout = std::io::stdout(); // get the global stdout entity
+    let mut handle = stdout.lock(); // acquire a lock on it
+    writeln!(handle, "{}", generated.rendered_metadata)?;
+    let message = match output.status {
+        WriteStatus::Created => "Successfully appeneded metadata".green(),
+        WriteStatus::Overwritten => "Successfully overwrote metadata".green(),
+        WriteStatus::Skipped => "Skipped existing file".yellow(),
+        WriteStatus::DryRun => "Dry run, nothing was written".yellow(),
+        WriteStatus::Streamed => "Streamed to stdout".green(),
+    };
+    writeln!(handle, "{}", message)?;
+    writeln!(handle, "{:<10}{}", "Title", generated.metadata.title)?;
+    if let Some(output_path) = output.output_path {
+        writeln!(handle, "{:<10}{}", "File", output_path.to_string_lossy())?;
+    }
+    if !generated.unmatched_genres.is_empty() {
+        writeln!(
+            handle,
+            "{}",
+            format!(
+                "Genres not in the taxonomy: {}",
+                generated.unmatched_genres.join(", ")
+            )
+            .yellow()
+        )?;
+    }
+    Ok(())
+}
+
+fn restore(params: BrainiacRestore, format: OutputFormat) -> Result<(), BrainiacError> {
+    let source_path = params.source_path.clone();
+    let backup_path = restore_article(params)?;
+    if format != OutputFormat::Text {
+        return print_json(
+            serde_json::json!({
+                "status": "restored",
+                "file": source_path,
+                "backup": backup_path,
+            }),
+            format,
+        );
+    }
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "{}", "Successfully restored article".green())?;
+    writeln!(handle, "{:<10}{}", "File", source_path.to_string_lossy())?;
+    writeln!(handle, "{:<10}{}", "Backup", backup_path.to_string_lossy())?;
+    Ok(())
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> ExitCode {
+    let app = Cli::parse();
+    // The default builder logs at info level or reads the level from RUST_LOG
     let mut builder = colog::default_builder();
-    builder.filter_level(log::LevelFilter::Trace);
+    if let Some(level) = app.log_level() {
+        builder.filter_level(level);
+    }
     builder.init();
-    let app = Cli::parse();
 
-    match app.command {
+    let format = app.output_format();
+    let config = match app.config_path.as_deref().map(Config::load).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(error) => {
+            print_error(&error, format);
+            return ExitCode::from(exit_code(error.category()));
+        }
+    };
+    let result = match app.command {
         Commands::Generate {
             source_path,
             output_dir_path,
-            gen_model,
-            format_model,
-            author,
+            generate: args,
+            on_collision,
+            dry_run,
+            in_place,
+            stdout,
+            metadata_only,
+            backup,
+            backup_dir_path,
+            stream,
+            interactive,
+            title_candidates,
         } => {
-            let params = BrainiacAppend {
-                source_path,
-                output_dir_path,
-                gen_model,
-                format_model,
-                author,
+            let source = if source_path.as_os_str() == "-" {
+                InputSource::Stdin
+            } else {
+                InputSource::File(source_path)
             };
-            match append_metadata(params).await {
-                Ok(metadata) => {
-                    let stdout = std::io::stdout(); // get the global stdout entity
-                    let mut handle = stdout.lock(); // acquire a lock on it
-                    writeln!(handle, "{}", "Successfully appeneded metadata".green())?;
-                    writeln!(handle, "{:<10}{}", "Title", metadata.title)?;
-                    writeln!(
-                        handle,
-                        "{:<10}{}",
-                        "File",
-                        create_output_file_name(&metadata.slug).to_string_lossy()
-                    )?;
-                    Ok(())
-                }
-                Err(error) => {
-                    let stderr = std::io::stderr(); // get the global stderr entity
-                    let mut handle = stderr.lock(); // acquire a lock on it
-                    writeln!(handle, "{}", error.to_string().red())?;
-                    Ok(())
+            let output = if in_place {
+                OutputTarget::InPlace
+            } else if stdout {
+                OutputTarget::Stdout
+            } else {
+                OutputTarget::Directory(output_dir_path.unwrap_or_default())
+            };
+            generate(
+                BrainiacAppend {
+                    source,
+                    output,
+                    generate: args.into(),
+                    on_collision,
+                    dry_run,
+                    backup: backup_from_args(backup, backup_dir_path),
+                },
+                format,
+                metadata_only,
+                stream,
+                interactive,
+                title_candidates,
+                config,
+            )
+            .await
+        }
+        Commands::Watch {
+            dir_path,
+            generate: args,
+            debounce_ms,
+            backup,
+            backup_dir_path,
+        } => {
+            let backup = backup_from_args(backup, backup_dir_path.clone());
+            match client_builder(args.into(), config, backup).build() {
+                Ok(client) => {
+                    watch::watch(
+                        client,
+                        dir_path,
+                        backup_dir_path,
+                        Duration::from_millis(debounce_ms),
+                        format,
+                    )
+                    .await
                 }
+                Err(error) => Err(error),
             }
         }
+        Commands::Serve {
+            address,
+            generate: args,
+            concurrency,
+            timeout_secs,
+        } => match client_builder(args.into(), config, None).build() {
+            Ok(client) => {
+                serve::serve(
+                    client,
+                    address,
+                    concurrency,
+                    Duration::from_secs(timeout_secs),
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        },
+        Commands::Lsp { generate: args } => match client_builder(args.into(), config, None).build()
+        {
+            Ok(client) => {
+                lsp::lsp(client).await;
+                Ok(())
+            }
+            Err(error) => Err(error),
+        },
+        Commands::Restore {
+            source_path,
+            backup_dir_path,
+        } => restore(
+            BrainiacRestore {
+                source_path,
+                backup: backup_from_args(true, backup_dir_path).unwrap_or(Backup::Sibling),
+            },
+            format,
+        ),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            print_error(&error, format);
+            ExitCode::from(exit_code(error.category()))
+        }
     }
 }
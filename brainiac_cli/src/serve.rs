@@ -0,0 +1,142 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use brainiac::{
+    error::{BrainiacError, ErrorCategory},
+    lint::LintIssue,
+    model::Metadata,
+    Brainiac,
+};
+use tokio::sync::Semaphore;
+
+use crate::exit_code;
+
+struct AppState {
+    client: Brainiac,
+    /// Limits how many articles are generated at once, further requests wait for a permit
+    permits: Semaphore,
+    timeout: Duration,
+}
+
+/// A `BrainiacError` returned as a JSON error body, in the same shape as the CLI JSON output.
+struct ApiError(StatusCode, BrainiacError);
+
+impl From<BrainiacError> for ApiError {
+    fn from(error: BrainiacError) -> Self {
+        let status = match error.category() {
+            ErrorCategory::Model => StatusCode::BAD_GATEWAY,
+            ErrorCategory::FrontMatter | ErrorCategory::Validation => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ErrorCategory::Config => StatusCode::BAD_REQUEST,
+            ErrorCategory::Collision => StatusCode::CONFLICT,
+            ErrorCategory::Io | ErrorCategory::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let ApiError(status, error) = self;
+        let body = serde_json::json!({
+            "error": {
+                "category": error.category().to_string(),
+                "message": error.to_string(),
+                "exit_code": exit_code(error.category()),
+            }
+        });
+        (status, Json(body)).into_response()
+    }
+}
+
+async fn metadata(
+    State(state): State<Arc<AppState>>,
+    content: String,
+) -> Result<Json<Metadata>, ApiError> {
+    // The time spent waiting for a permit counts towards the timeout
+    let generate = async {
+        let _permit = state
+            .permits
+            .acquire()
+            .await
+            .map_err(|error| BrainiacError::IoError(std::io::Error::other(error)))?;
+        state.client.generate_metadata(&content).await
+    };
+    let generated = tokio::time::timeout(state.timeout, generate)
+        .await
+        .map_err(|_| {
+            ApiError(
+                StatusCode::GATEWAY_TIMEOUT,
+                BrainiacError::TimeoutError(state.timeout),
+            )
+        })??;
+    Ok(Json(generated.metadata))
+}
+
+async fn parse(
+    State(state): State<Arc<AppState>>,
+    content: String,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let parsed = state.client.parse(&content)?;
+    Ok(Json(match parsed {
+        Some(parsed) => serde_json::json!({
+            "metadata": parsed.data,
+            "content": parsed.content,
+        }),
+        None => serde_json::json!({
+            "metadata": null,
+            "content": content,
+        }),
+    }))
+}
+
+async fn lint(
+    State(state): State<Arc<AppState>>,
+    content: String,
+) -> Result<Json<Vec<LintIssue>>, ApiError> {
+    Ok(Json(state.client.lint(&content)?))
+}
+
+/// Serve the metadata API on `address` until ctrl-c.
+/// Every endpoint takes the markdown article as the request body.
+pub async fn serve(
+    client: Brainiac,
+    address: SocketAddr,
+    concurrency: usize,
+    timeout: Duration,
+) -> Result<(), BrainiacError> {
+    if concurrency == 0 {
+        return Err(BrainiacError::ConfigError(
+            None,
+            "concurrency must be greater than 0".to_string(),
+        ));
+    }
+    let state = Arc::new(AppState {
+        client,
+        permits: Semaphore::new(concurrency),
+        timeout,
+    });
+    let app = Router::new()
+        .route("/metadata", post(metadata))
+        .route("/parse", post(parse))
+        .route("/lint", post(lint))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    log::info!("Listening on http://{}", address);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
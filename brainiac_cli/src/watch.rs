@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use brainiac::{
+    error::{BrainiacError, FileOperation},
+    has_current_metadata, Brainiac, InputSource, OutputTarget,
+};
+use colored::Colorize;
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+use std::io::Write;
+
+use crate::cli::OutputFormat;
+use crate::{print_error, print_json};
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("md"))
+}
+
+/// Update the metadata of the article at `path` unless it is already current.
+/// The metadata written by a previous update is current, so our own writes do not loop.
+async fn update(client: &Brainiac, path: PathBuf, format: OutputFormat) {
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) => {
+            print_error(
+                &BrainiacError::file(FileOperation::Read, &path)(error),
+                format,
+            );
+            return;
+        }
+    };
+    if content.trim().is_empty() || has_current_metadata(&content) {
+        log::debug!("Metadata of {} is current", path.display());
+        return;
+    }
+    log::info!("Updating {}", path.display());
+    let output = match client
+        .append(&InputSource::File(path), &OutputTarget::InPlace, false)
+        .await
+    {
+        Ok(output) => output,
+        Err(error) => return print_error(&error, format),
+    };
+    let file = output.output_path.unwrap_or_default();
+    if format != OutputFormat::Text {
+        let _ = print_json(
+            serde_json::json!({
+                "status": output.status.to_string(),
+                "file": file,
+                "metadata": output.generated.metadata,
+                "unmatched_genres": output.generated.unmatched_genres,
+            }),
+            format,
+        );
+        return;
+    }
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    let _ = writeln!(
+        handle,
+        "{} {} ({})",
+        "Updated".green(),
+        file.to_string_lossy(),
+        output.generated.metadata.title
+    );
+}
+
+/// Watch `directory` and update the metadata of every markdown file that is saved, until ctrl-c.
+/// Files inside `ignore`, such as the backup directory, are left alone.
+pub async fn watch(
+    client: Brainiac,
+    directory: PathBuf,
+    ignore: Option<PathBuf>,
+    debounce: Duration,
+    format: OutputFormat,
+) -> Result<(), BrainiacError> {
+    let directory = directory
+        .canonicalize()
+        .map_err(BrainiacError::file(FileOperation::Read, &directory))?;
+    // Event paths are under the canonical directory, so the ignored directory has to be
+    // canonical as well. It is created up front because it only exists after the first backup.
+    let ignore = ignore
+        .map(|path| {
+            std::fs::create_dir_all(&path)
+                .and_then(|_| path.canonicalize())
+                .map_err(BrainiacError::file(FileOperation::Backup, &path))
+        })
+        .transpose()?;
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(debounce, move |result: DebounceEventResult| {
+        let _ = sender.send(result);
+    })
+    .map_err(|error| BrainiacError::ConfigError(None, error.to_string()))?;
+    debouncer
+        .watcher()
+        .watch(&directory, RecursiveMode::Recursive)
+        .map_err(|error| BrainiacError::ConfigError(Some(directory.clone()), error.to_string()))?;
+    log::info!("Watching {}", directory.display());
+    // Reading a file raises an event as well, so a file that failed to update is only tried
+    // again once it is modified
+    let mut attempted: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    loop {
+        let events = tokio::select! {
+            events = receiver.recv() => events,
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        let events = match events {
+            Some(Ok(events)) => events,
+            Some(Err(error)) => {
+                log::warn!("Watch error: {}", error);
+                continue;
+            }
+            None => break,
+        };
+        for event in events {
+            let ignored = ignore
+                .as_ref()
+                .is_some_and(|ignore| event.path.starts_with(ignore));
+            if ignored || !is_markdown(&event.path) || !event.path.is_file() {
+                continue;
+            }
+            let modified = std::fs::metadata(&event.path).and_then(|metadata| metadata.modified());
+            if let Ok(modified) = modified {
+                if attempted.insert(event.path.clone(), modified) == Some(modified) {
+                    continue;
+                }
+            }
+            update(&client, event.path, format).await;
+        }
+    }
+    Ok(())
+}